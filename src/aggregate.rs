@@ -0,0 +1,133 @@
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::number;
+use crate::{DeliveryOrder, Trade};
+
+/// Merge the consecutive fills of same account, same day, same code and same direction into one row.
+///
+/// The quantity, amount and fees are summed, the price is the average weighted by
+/// quantity, and 证券余额 is taken from the last fill. Only buy and sell rows
+/// are merged, the position of merged row is the first fill. The fills apart by
+/// other rows of the code are not merged, so 证券余额 of the rows keep in order.
+pub fn aggregate_fills(orders: Vec<DeliveryOrder>) -> Vec<DeliveryOrder> {
+    let mut ret: Vec<DeliveryOrder> = vec![];
+    // (account, code) => (date, kind, index in ret, total of price * count) of the last fill
+    let mut merged: HashMap<(String, String), (String, String, usize, Decimal)> = HashMap::new();
+
+    for order in orders {
+        let key = (order.get_account().clone(), order.get_code().clone());
+
+        if order.get_trade() != &Trade::Buy && order.get_trade() != &Trade::Sell {
+            merged.remove(&key);
+            ret.push(order);
+            continue;
+        }
+        let count = order.get_count();
+        let cost = order.get_prize().unwrap_or_default() * count.abs();
+        let last = merged
+            .get_mut(&key)
+            .filter(|(date, kind, _, _)| date == order.get_date() && kind == order.get_kind());
+
+        if let Some((_, _, idx, total)) = last {
+            let last = &mut ret[*idx];
+            let count = last.get_count() + count;
+
+            *total += cost;
+            last.set_count(count);
+            if last.get_amount().is_some() || order.get_amount().is_some() {
+                let amount =
+                    last.get_amount().unwrap_or_default() + order.get_amount().unwrap_or_default();
+
+                last.set_amount(Some(number::round(amount, 2)));
+            }
+            for (get, set) in [
+                (
+                    DeliveryOrder::get_fee as fn(&DeliveryOrder) -> Option<Decimal>,
+                    DeliveryOrder::set_fee as fn(&mut DeliveryOrder, Option<Decimal>),
+                ),
+                (DeliveryOrder::get_commission, DeliveryOrder::set_commission),
+                (DeliveryOrder::get_stamp_tax, DeliveryOrder::set_stamp_tax),
+                (
+                    DeliveryOrder::get_transfer_fee,
+                    DeliveryOrder::set_transfer_fee,
+                ),
+            ] {
+                if get(last).is_some() || get(&order).is_some() {
+                    let fee = get(last).unwrap_or_default() + get(&order).unwrap_or_default();
+
+                    set(last, Some(number::round(fee, 2)));
+                }
+            }
+            if !count.is_zero() {
+                last.set_prize(Some(number::round(*total / count.abs(), 3)));
+            }
+            last.set_owned(order.get_owned());
+        } else {
+            merged.insert(
+                key,
+                (
+                    order.get_date().clone(),
+                    order.get_kind().clone(),
+                    ret.len(),
+                    cost,
+                ),
+            );
+            ret.push(order);
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(trade: Trade, count: i64, price: i64, owned: i64) -> DeliveryOrder {
+        let sign = if trade == Trade::Buy { -1 } else { 1 };
+
+        DeliveryOrder::default()
+            .with_date("20230103".to_owned())
+            .with_code("600000".to_owned())
+            .with_kind(trade.label().to_owned())
+            .with_count(Decimal::from(count))
+            .with_prize(Some(Decimal::from(price)))
+            .with_amount(Some(Decimal::from(sign * count * price)))
+            .with_owned(Some(Decimal::from(owned)))
+            .with_trade(trade)
+    }
+
+    #[test]
+    fn merge_consecutive_fills() {
+        let orders = aggregate_fills(vec![
+            fill(Trade::Buy, 100, 10, 100),
+            fill(Trade::Buy, 300, 12, 400),
+        ]);
+
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].get_count(), Decimal::from(400));
+        assert_eq!(orders[0].get_prize(), Some(Decimal::new(11500, 3)));
+        assert_eq!(orders[0].get_amount(), Some(Decimal::from(-4600)));
+        assert_eq!(orders[0].get_owned(), Some(Decimal::from(400)));
+    }
+
+    #[test]
+    fn keep_fills_apart_by_other_rows() {
+        let orders = aggregate_fills(vec![
+            fill(Trade::Buy, 100, 10, 100),
+            fill(Trade::Sell, 100, 11, 0),
+            fill(Trade::Buy, 100, 10, 100),
+        ]);
+
+        assert_eq!(orders.len(), 3);
+        assert_eq!(
+            orders.iter().map(|v| v.get_owned()).collect::<Vec<_>>(),
+            vec![
+                Some(Decimal::from(100)),
+                Some(Decimal::ZERO),
+                Some(Decimal::from(100))
+            ]
+        );
+    }
+}
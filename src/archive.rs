@@ -0,0 +1,47 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &'static str = "manifest.txt";
+
+/// Move the processed statement `path` into `dir`, and record it in the manifest of `dir`.
+///
+/// Refuse to overwrite an archived file with same name, it is most likely processed already.
+pub fn archive(dir: &str, path: &str) -> std::io::Result<PathBuf> {
+    let dir = Path::new(dir);
+    let name = Path::new(path).file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid input path: {}", path),
+        )
+    })?;
+    let dest = dir.join(name);
+
+    fs::create_dir_all(dir)?;
+    if dest.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} is archived already: {}", path, dest.display()),
+        ));
+    }
+    // rename not works across file systems
+    if fs::rename(path, &dest).is_err() {
+        fs::copy(path, &dest)?;
+        fs::remove_file(path)?;
+    }
+
+    let mut manifest = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(MANIFEST))?;
+
+    writeln!(
+        manifest,
+        "{}\t{}\t{}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        path,
+        dest.display()
+    )?;
+
+    Ok(dest)
+}
@@ -0,0 +1,99 @@
+use colored::Colorize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Info => "信息",
+            Level::Warning => "警告",
+            Level::Error => "错误",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    level: Level,
+    path: String,
+    line: usize,
+    message: String,
+}
+
+impl Record {
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_message(&self) -> &String {
+        &self.message
+    }
+}
+
+/// Audit log of the things happened during extraction, such as derived values.
+#[derive(Debug, Default, Clone)]
+pub struct Audit {
+    records: Vec<Record>,
+}
+
+impl Audit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, level: Level, path: &str, line: usize, message: String) {
+        self.records.push(Record {
+            level,
+            path: path.to_owned(),
+            line,
+            message,
+        });
+    }
+
+    /// Append the records of `other`, such as the log of a forked context.
+    pub fn extend(&mut self, other: Audit) {
+        self.records.extend(other.records);
+    }
+
+    pub fn get_records(&self) -> &Vec<Record> {
+        &self.records
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Count of the records which level is not less than `level`.
+    pub fn count(&self, level: Level) -> usize {
+        self.records.iter().filter(|v| v.level >= level).count()
+    }
+
+    /// Print the records which level is not less than `level`.
+    pub fn print(&self, level: Level) {
+        for record in self.records.iter().filter(|v| v.level >= level) {
+            let label = match record.level {
+                Level::Info => record.level.label().normal(),
+                Level::Warning => record.level.label().yellow(),
+                Level::Error => record.level.label().red(),
+            };
+
+            eprintln!(
+                "[{}] {}:{} {}",
+                label, record.path, record.line, record.message
+            );
+        }
+    }
+}
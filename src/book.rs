@@ -0,0 +1,75 @@
+use crate::DeliveryOrder;
+
+/// All the orders converted in one run, in output order.
+#[derive(Debug, Default, Clone)]
+pub struct OrderBook {
+    orders: Vec<DeliveryOrder>,
+}
+
+impl OrderBook {
+    pub fn new(orders: Vec<DeliveryOrder>) -> Self {
+        Self { orders }
+    }
+
+    pub fn get_orders(&self) -> &Vec<DeliveryOrder> {
+        &self.orders
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Convert the orders into a typed DataFrame, column names are same as the Arrow output.
+    #[cfg(feature = "polars")]
+    pub fn to_dataframe(&self) -> polars::prelude::PolarsResult<polars::prelude::DataFrame> {
+        use polars::prelude::{DataFrame, NamedFrom, Series};
+        use rust_decimal::prelude::ToPrimitive;
+        use rust_decimal::Decimal;
+
+        let text = |name: &str, get: fn(&DeliveryOrder) -> &String| {
+            Series::new(
+                name,
+                self.orders
+                    .iter()
+                    .map(|v| get(v).as_str())
+                    .collect::<Vec<&str>>(),
+            )
+        };
+        let int = |name: &str, get: fn(&DeliveryOrder) -> Option<Decimal>| {
+            Series::new(
+                name,
+                self.orders
+                    .iter()
+                    .map(|v| get(v).and_then(|v| v.trunc().to_i64()))
+                    .collect::<Vec<Option<i64>>>(),
+            )
+        };
+        let float = |name: &str, get: fn(&DeliveryOrder) -> Option<Decimal>| {
+            Series::new(
+                name,
+                self.orders
+                    .iter()
+                    .map(|v| get(v).and_then(|v| v.to_f64()))
+                    .collect::<Vec<Option<f64>>>(),
+            )
+        };
+
+        DataFrame::new(vec![
+            text("date", DeliveryOrder::get_date),
+            text("code", DeliveryOrder::get_code),
+            text("name", DeliveryOrder::get_name),
+            text("kind", DeliveryOrder::get_kind),
+            Series::new(
+                "count",
+                self.orders
+                    .iter()
+                    .map(|v| v.get_count().to_i64())
+                    .collect::<Vec<Option<i64>>>(),
+            ),
+            float("price", DeliveryOrder::get_prize),
+            float("amount", DeliveryOrder::get_amount),
+            int("owned", DeliveryOrder::get_owned),
+            text("account", DeliveryOrder::get_account),
+        ])
+    }
+}
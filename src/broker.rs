@@ -0,0 +1,149 @@
+use async_std::sync::Arc;
+use encoding_rs::{Encoding, GBK};
+use std::fmt::Debug;
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+
+use crate::citic;
+use crate::custom;
+use crate::eastmoney;
+use crate::error::{Error, ParseError};
+use crate::htsc::{self, Context};
+use crate::mapping::Sign;
+use crate::moomoo;
+use crate::pingan;
+use crate::reader;
+use crate::robinhood;
+use crate::sheet;
+use crate::tonghuashun;
+use crate::tzzb;
+use crate::zhaoshang;
+use crate::zipped;
+use crate::DeliveryOrder;
+
+/// The orders of a file read at once, see [`BrokerParser::read_file`].
+pub type FileOrders<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<DeliveryOrder>, Error>> + Send + 'a>>;
+
+/// The broker specific part of parsing a delivery order statement.
+///
+/// The statements are read line by line by [`htsc::Context`], which handles the
+/// sections, footers, balances and the mapping of 业务名称 for all brokers.
+pub trait BrokerParser: Debug + Send + Sync {
+    /// Name of the broker, selected by `--type`.
+    fn get_name(&self) -> &'static str;
+
+    /// Return true if the `line` is a header of this broker's statement.
+    fn detect(&self, line: &str) -> bool;
+
+    /// Encoding of the statement if it has no byte order mark.
+    fn get_encoding(&self) -> &'static Encoding {
+        GBK
+    }
+
+    /// Sign convention of the statement, overridden by the `[sign]` of config.
+    fn get_sign(&self) -> Sign {
+        Sign::Signed
+    }
+
+    /// Rename a column of the statement to the name understood by [`Context::gen_order`].
+    fn map_title(&self, title: &str) -> String {
+        title.to_owned()
+    }
+
+    /// Normalize the value of a mapped column, such as the date or 业务名称.
+    fn map_value(&self, _title: &str, value: &str) -> String {
+        value.to_owned()
+    }
+
+    /// Convert a detail line into order, the `titles` are mapped already.
+    fn parse_line(
+        &self,
+        ctx: &mut Context,
+        titles: &Vec<String>,
+        line: &str,
+    ) -> Result<DeliveryOrder, ParseError> {
+        ctx.gen_order(titles, line.to_owned())
+    }
+
+    /// Read all the orders of `path`, for the inputs not read line by line, such as
+    /// the CSV exports of overseas brokers.
+    ///
+    /// `None` if the file is a statement read by [`Context`], which is the default.
+    fn read_file<'a>(&'a self, _ctx: &'a mut Context, _path: &'a str) -> Option<FileOrders<'a>> {
+        None
+    }
+}
+
+// the header may follow some title lines of account information
+const DETECT_BYTES: u64 = 16 * 1024;
+const DETECT_LINES: usize = 20;
+
+/// All the brokers supported, the more specific header goes first.
+///
+/// The workbook generated by this tool goes first, then the custom broker of config,
+/// and the built-in ones with more specific signatures go before the others.
+pub fn registry() -> Vec<Arc<dyn BrokerParser>> {
+    let mut brokers: Vec<Arc<dyn BrokerParser>> = vec![Arc::new(tzzb::Tzzb)];
+
+    if let Some(custom) = custom::get() {
+        brokers.push(custom);
+    }
+    brokers.extend([
+        Arc::new(pingan::Pingan) as Arc<dyn BrokerParser>,
+        Arc::new(citic::Citic),
+        Arc::new(zhaoshang::Zhaoshang),
+        // the header of 东方财富 may have the columns of 同花顺
+        Arc::new(eastmoney::Eastmoney),
+        Arc::new(tonghuashun::Tonghuashun),
+        // the `Code` of moomoo is in the `Trans Code` of Robinhood
+        Arc::new(robinhood::Robinhood),
+        Arc::new(moomoo::Moomoo),
+        Arc::new(htsc::Htsc),
+    ]);
+    brokers
+}
+
+/// Find the broker by name of `--type`.
+pub fn find(name: &str) -> Option<Arc<dyn BrokerParser>> {
+    registry().into_iter().find(|v| v.get_name() == name)
+}
+
+/// Detect the broker of statement `path` by the header signatures, the
+/// statement may be a spreadsheet.
+///
+/// The first lines are decoded with the encoding guessed from content, or the
+/// encoding of each broker, and the first broker in [`registry`] which
+/// recognizes a header wins.
+pub fn detect(path: &str) -> Option<Arc<dyn BrokerParser>> {
+    if sheet::is_sheet_path(path) {
+        let lines = sheet::read_lines(path).ok()?;
+
+        return registry().into_iter().find(|broker| {
+            lines
+                .iter()
+                .take(DETECT_LINES)
+                .any(|(_, line)| broker.detect(line))
+        });
+    }
+    let mut head = vec![];
+
+    if path == reader::STDIN_PATH {
+        head = reader::read_stdin().ok()?.to_vec();
+    } else if zipped::split_entry(path).is_some() {
+        head = zipped::read_entry(path).ok()?;
+    } else {
+        std::fs::File::open(path)
+            .and_then(|file| file.take(DETECT_BYTES).read_to_end(&mut head))
+            .ok()?;
+    }
+    registry().into_iter().find(|broker| {
+        let encoding = reader::sniff(&head).unwrap_or(broker.get_encoding());
+        let (text, _, _) = encoding.decode(&head);
+
+        text.lines()
+            .take(DETECT_LINES)
+            .any(|line| broker.detect(line))
+    })
+}
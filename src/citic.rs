@@ -0,0 +1,31 @@
+use crate::broker::BrokerParser;
+
+const HEADER_KEYS: [&'static str; 2] = ["清算金额", "委托价格"];
+
+/// The delivery order exported by 中信证券, tab-separated GBK text like 华泰证券
+/// with different column names.
+#[derive(Debug)]
+pub struct Citic;
+
+impl BrokerParser for Citic {
+    fn get_name(&self) -> &'static str {
+        crate::CITIC_TYPE
+    }
+
+    /// Both of the keys are required, 清算金额 alone is also in the header of 平安证券.
+    fn detect(&self, line: &str) -> bool {
+        HEADER_KEYS.iter().all(|key| line.contains(key))
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        match title {
+            "成交日期" => "发生日期",
+            "委托价格" => "成交价格",
+            "清算金额" => "发生金额",
+            "操作" | "摘要" => "业务名称",
+            "股份余额" | "剩余数量" => "证券数量",
+            title => title,
+        }
+        .to_owned()
+    }
+}
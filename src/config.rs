@@ -0,0 +1,135 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::custom::Mapping;
+use crate::mail::Smtp;
+use crate::mapping::{Category, Rule, Sign};
+use crate::round::Rounding;
+use crate::writer::beancount::Accounts;
+use crate::Trade;
+
+/// Change the set of ignored 业务名称.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Ignore {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+/// Configuration loaded from `--config` file.
+///
+/// ```toml
+/// output = "2023.xlsx"
+/// type = "HTSC"
+///
+/// [alias]
+/// "成交日期" = "发生日期"
+///
+/// [custom]
+/// date = "交收日期"
+/// kind = "摘要"
+///
+/// [custom.trade]
+/// "证券买入" = "Buy"
+///
+/// [business]
+/// "证券买入(普通)" = "证券买入"
+///
+/// [trade]
+/// "红利入账" = "In"
+/// "申购配号" = "Ignore"
+/// "股息入账" = "分红"
+///
+/// [category.分红]
+/// label = "分红"
+/// holdings = "none"
+/// cash = true
+/// sign = 1
+///
+/// [[rule]]
+/// field = "摘要"
+/// pattern = "^股息红利.*扣税$"
+/// trade = "Out"
+/// sign = -1
+///
+/// [unit.HTSC]
+/// "成交数量" = 100
+///
+/// [sign]
+/// HTSC = "unsigned"
+/// PINGAN = "signed"
+///
+/// [ignore]
+/// add = ["新股申购"]
+/// remove = ["托管转入"]
+///
+/// [account]
+/// "666600001234" = "爸爸-华泰"
+///
+/// [round]
+/// mode = "half-even"
+/// price = 3
+/// amount = 2
+/// fee = 2
+///
+/// [rate]
+/// USD = { "2023" = "7.0827" }
+/// HKD = { "2023" = "0.9062" }
+///
+/// [smtp]
+/// host = "smtp.example.com"
+/// username = "me@example.com"
+/// password = "secret"
+/// from = "me@example.com"
+/// to = ["me@example.com"]
+///
+/// [beancount]
+/// cash = "Assets:Huatai:Cash"
+/// securities = "Assets:Huatai:Stock"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// default of `--output`
+    pub output: Option<String>,
+    /// default of `--type`
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// column name in statement => the column name understood, such as 发生日期
+    pub alias: HashMap<String, String>,
+    /// 业务名称 => Trade, extend or override the built-in mapping
+    pub trade: HashMap<String, Trade>,
+    /// 业务名称 spelled by some brokers => the name in mapping
+    pub business: HashMap<String, String>,
+    /// user defined categories beyond the built-in Buy/Sell/In/Out
+    pub category: HashMap<String, Category>,
+    /// regex rules evaluated in order before the mapping
+    pub rule: Vec<Rule>,
+    /// broker name => column => shares per unit, for the quantity columns reported in lots (手)
+    pub unit: HashMap<String, HashMap<String, i64>>,
+    /// broker name => sign convention of its statements, signed if not set
+    pub sign: HashMap<String, Sign>,
+    /// noise rows ignored beside the built-in ones
+    pub ignore: Ignore,
+    /// account number => friendly name shown in output
+    pub account: HashMap<String, String>,
+    /// decimal places of price, amount and fees in output
+    pub round: Rounding,
+    /// email the summary and output after each run
+    pub smtp: Option<Smtp>,
+    /// account names of `--format beancount`
+    pub beancount: Accounts,
+    /// columns of the broker not supported, selected by `--type CUSTOM`
+    pub custom: Mapping,
+    /// currency => year => RMB per unit, converting the overseas incomes of `report --foreign-income`
+    pub rate: HashMap<String, HashMap<String, Decimal>>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> color_eyre::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+
+        Ok(toml::from_str(&content)?)
+    }
+}
@@ -0,0 +1,131 @@
+use async_std::sync::Arc;
+use encoding_rs::{Encoding, GBK};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::broker::BrokerParser;
+use crate::date;
+use crate::Trade;
+
+static CUSTOM: OnceLock<Arc<Custom>> = OnceLock::new();
+
+/// Column names of a statement not supported, given in `[custom]` of config.
+///
+/// ```toml
+/// [custom]
+/// date = "交收日期"
+/// amount = "清算金额"
+/// encoding = "utf-8"
+///
+/// [custom.trade]
+/// "证券买入清算" = "Buy"
+/// "证券卖出清算" = "Sell"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Mapping {
+    pub date: Option<String>,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    /// the column of 业务名称
+    pub kind: Option<String>,
+    pub count: Option<String>,
+    pub price: Option<String>,
+    pub amount: Option<String>,
+    pub owned: Option<String>,
+    pub account: Option<String>,
+    /// 业务名称 => Trade
+    pub trade: HashMap<String, Trade>,
+    /// label of encoding if the statement has no byte order mark, default is GBK
+    pub encoding: Option<String>,
+}
+
+impl Mapping {
+    pub fn is_empty(&self) -> bool {
+        self.columns().is_empty()
+    }
+
+    /// The pairs of column in statement and the name understood by [`crate::htsc::Context`].
+    pub fn columns(&self) -> Vec<(&String, &'static str)> {
+        [
+            (&self.date, "发生日期"),
+            (&self.code, "证券代码"),
+            (&self.name, "证券名称"),
+            (&self.kind, "业务名称"),
+            (&self.count, "成交数量"),
+            (&self.price, "成交价格"),
+            (&self.amount, "发生金额"),
+            (&self.owned, "证券数量"),
+            (&self.account, "资金账号"),
+        ]
+        .into_iter()
+        .filter_map(|(column, title)| column.as_ref().map(|v| (v, title)))
+        .collect()
+    }
+}
+
+/// The statement of broker described by a [`Mapping`], selected by `--type CUSTOM`.
+///
+/// The header is recognized if it has all the columns of mapping, the columns
+/// not in mapping are kept as is, so the known names such as 证券代码 work without mapping.
+#[derive(Debug)]
+pub struct Custom {
+    // column in statement => the name understood
+    titles: HashMap<String, &'static str>,
+    encoding: &'static Encoding,
+}
+
+impl Custom {
+    pub fn new(mapping: &Mapping, encoding: Option<&'static Encoding>) -> Self {
+        Self {
+            titles: mapping
+                .columns()
+                .into_iter()
+                .map(|(column, title)| (column.clone(), title))
+                .collect(),
+            encoding: encoding.unwrap_or(GBK),
+        }
+    }
+}
+
+impl BrokerParser for Custom {
+    fn get_name(&self) -> &'static str {
+        crate::CUSTOM_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        !self.titles.is_empty() && self.titles.keys().all(|v| line.contains(v.as_str()))
+    }
+
+    fn get_encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        self.titles
+            .get(title)
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| title.to_owned())
+    }
+
+    fn map_value(&self, title: &str, value: &str) -> String {
+        match title {
+            "发生日期" => date::parse_date(value)
+                .map(|v| v.format("%Y%m%d").to_string())
+                .unwrap_or_else(|| value.to_owned()),
+            _ => value.to_owned(),
+        }
+    }
+}
+
+/// Register the custom broker, it goes first in [`crate::broker::registry`].
+///
+/// Only the first registration takes effect.
+pub fn register(custom: Custom) {
+    let _ = CUSTOM.set(Arc::new(custom));
+}
+
+pub fn get() -> Option<Arc<Custom>> {
+    CUSTOM.get().cloned()
+}
@@ -0,0 +1,111 @@
+use chrono::NaiveDate;
+
+const FORMATS: [&'static str; 3] = ["%Y%m%d", "%Y-%m-%d", "%Y/%m/%d"];
+
+/// Parse the date of delivery order, the brokers use different formats.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+
+    FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(value, fmt).ok())
+}
+
+/// Render the date `value` with `format`, keep as it is if no format given or it can't be parsed.
+pub fn format_date(value: &str, format: Option<&str>) -> String {
+    match (format, parse_date(value)) {
+        (Some(format), Some(date)) => date.format(format).to_string(),
+        _ => value.to_owned(),
+    }
+}
+
+/// Return true if the chrono `format` has no unknown specifier, which panics in rendering.
+pub fn is_valid_format(format: &str) -> bool {
+    use chrono::format::{Item, StrftimeItems};
+
+    StrftimeItems::new(format).all(|v| !matches!(v, Item::Error))
+}
+
+/// Return true if the date `value` is between `from` and `to`, both inclusive.
+///
+/// The dates can't be parsed are out of any range.
+pub fn in_range(value: &str, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    match parse_date(value) {
+        Some(date) => {
+            from.map(|v| date >= v).unwrap_or(true) && to.map(|v| date <= v).unwrap_or(true)
+        }
+        None => from.is_none() && to.is_none(),
+    }
+}
+
+/// Month index since year 0, for compare the month of dates.
+pub fn month_of(date: &NaiveDate) -> i32 {
+    use chrono::Datelike;
+
+    date.year() * 12 + date.month0() as i32
+}
+
+/// Translate the chrono `format` of date into the number format of Excel.
+///
+/// The specifiers Excel doesn't know are dropped, other characters are quoted as literal.
+pub fn excel_format(format: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = format.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '%' => {
+                let mut spec = chars.next();
+                let padding = spec != Some('-');
+
+                if !padding {
+                    spec = chars.next();
+                }
+                ret.push_str(match (spec, padding) {
+                    (Some('Y'), _) => "yyyy",
+                    (Some('y'), _) => "yy",
+                    (Some('m'), true) => "mm",
+                    (Some('m'), false) => "m",
+                    (Some('d'), true) => "dd",
+                    (Some('d'), false) | (Some('e'), _) => "d",
+                    (Some('b'), _) => "mmm",
+                    (Some('B'), _) => "mmmm",
+                    (Some('a'), _) => "ddd",
+                    (Some('A'), _) => "dddd",
+                    (Some('%'), _) => "\"%\"",
+                    _ => "",
+                });
+            }
+            '-' | '/' | '.' | ' ' | ':' | ',' => ret.push(ch),
+            ch => {
+                ret.push('"');
+                ret.push(ch);
+                ret.push('"');
+            }
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 3);
+
+        assert_eq!(parse_date("20230103"), date);
+        assert_eq!(parse_date(" 2023-01-03 "), date);
+        assert_eq!(parse_date("2023/01/03"), date);
+        assert_eq!(parse_date("03/01/2023"), None);
+        assert_eq!(parse_date(""), None);
+    }
+
+    #[test]
+    fn validate_format() {
+        assert!(is_valid_format("%Y-%m-%d"));
+        assert!(is_valid_format("%Y年%m月%d日"));
+        assert!(!is_valid_format("%Y-%Q"));
+    }
+}
@@ -0,0 +1,33 @@
+use crate::broker::BrokerParser;
+
+// the direction column is named differently from the other brokers
+const HEADER_KEYS: [&'static str; 2] = ["委托方向", "买卖标志"];
+
+/// The transaction history exported by 东方财富 APP, in xlsx.
+///
+/// The header may follow some title rows, the rows are read as the lines of
+/// a statement saved as spreadsheet, see [`crate::sheet::read_lines`].
+#[derive(Debug)]
+pub struct Eastmoney;
+
+impl BrokerParser for Eastmoney {
+    fn get_name(&self) -> &'static str {
+        crate::EASTMONEY_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.contains("证券代码") && HEADER_KEYS.iter().any(|key| line.contains(key))
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        match title {
+            "成交日期" | "交收日期" => "发生日期",
+            "委托方向" | "买卖标志" | "操作" | "业务类型" => "业务名称",
+            "成交均价" => "成交价格",
+            "清算金额" => "发生金额",
+            "股份余额" | "股票余额" => "证券数量",
+            title => title,
+        }
+        .to_owned()
+    }
+}
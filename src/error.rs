@@ -0,0 +1,76 @@
+use thiserror::Error as ThisError;
+
+/// Error of a malformed line, the file and line number are recorded by the audit log.
+#[derive(Debug, Clone, PartialEq, ThisError)]
+pub enum ParseError {
+    #[error("can not parse {value:?} of {column} as number")]
+    Number { column: String, value: String },
+
+    #[error("expect {expect} columns but got {got}")]
+    Columns { expect: usize, got: usize },
+}
+
+/// Error of extracting the orders from input files.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("not support current file: {0}")]
+    Unsupported(String),
+
+    #[error("{path}:{line}: {source}")]
+    Malformed {
+        path: String,
+        line: usize,
+        source: ParseError,
+    },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Sheet(#[from] calamine::Error),
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+
+    #[error(transparent)]
+    Write(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A line dropped by `--on-error skip` or `--on-error report`.
+#[derive(Debug, Clone)]
+pub struct Malformed {
+    path: String,
+    line: usize,
+    error: ParseError,
+    content: String,
+}
+
+impl Malformed {
+    pub fn new(path: String, line: usize, error: ParseError, content: String) -> Self {
+        Self {
+            path,
+            line,
+            error,
+            content,
+        }
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn get_error(&self) -> &ParseError {
+        &self.error
+    }
+
+    pub fn get_content(&self) -> &String {
+        &self.content
+    }
+}
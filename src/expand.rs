@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::Path;
+
+// the files picked from directory, the workbooks of last run are picked too and read as TZZB
+const INPUT_EXTENSIONS: [&'static str; 5] = ["txt", "csv", "xlsx", "xls", "zip"];
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn is_input_file(path: &Path) -> bool {
+    path.extension()
+        .map(|v| v.to_string_lossy().to_lowercase())
+        .map(|v| INPUT_EXTENSIONS.contains(&v.as_str()))
+        .unwrap_or(false)
+}
+
+fn is_same_file(path: &Path, other: Option<&Path>) -> bool {
+    match (fs::canonicalize(path), other.map(fs::canonicalize)) {
+        (Ok(path), Some(Ok(other))) => path == other,
+        _ => false,
+    }
+}
+
+fn list_dir(
+    dir: &Path,
+    recursive: bool,
+    output: Option<&Path>,
+    files: &mut Vec<String>,
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            if recursive {
+                list_dir(&path, recursive, output, files)?;
+            }
+        } else if is_input_file(&path) && !is_same_file(&path, output) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Expand the input argument into files, sorted by path.
+///
+/// A directory is expanded to the input files in it, the sub directories are
+/// included with `recursive`. A glob like `data/2023-*.txt` is expanded to
+/// the matched files. Other paths are returned as is.
+///
+/// The `output` about to be written is skipped by the directories and globs.
+pub fn expand(path: &str, recursive: bool, output: Option<&str>) -> std::io::Result<Vec<String>> {
+    let output = output.map(Path::new);
+    let mut files = vec![];
+
+    if Path::new(path).is_dir() {
+        list_dir(Path::new(path), recursive, output, &mut files)?;
+    } else if is_glob(path) {
+        let paths = glob::glob(path)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+        for path in paths {
+            let path = path.map_err(|e| e.into_error())?;
+
+            if path.is_dir() {
+                list_dir(&path, recursive, output, &mut files)?;
+            } else if !is_same_file(&path, output) {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    } else {
+        return Ok(vec![path.to_owned()]);
+    }
+    files.sort();
+    Ok(files)
+}
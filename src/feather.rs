@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::DeliveryOrder;
+
+/// Return true if the output `path` should be written as Arrow IPC (Feather v2).
+pub fn is_arrow_path(path: &str) -> bool {
+    let path = path.to_lowercase();
+
+    path.ends_with(".arrow") || path.ends_with(".feather")
+}
+
+/// Schema of the Arrow output, numbers are typed so readers don't need to parse text.
+pub fn schema(with_source: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("date", DataType::Utf8, false),
+        Field::new("code", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("count", DataType::Int64, true),
+        Field::new("price", DataType::Float64, true),
+        Field::new("amount", DataType::Float64, true),
+        Field::new("owned", DataType::Int64, true),
+        Field::new("account", DataType::Utf8, false),
+    ];
+
+    if with_source {
+        fields.push(Field::new("source", DataType::Utf8, false));
+        fields.push(Field::new("line", DataType::UInt64, false));
+    }
+    Schema::new(fields)
+}
+
+pub fn to_record_batch(
+    orders: &[DeliveryOrder],
+    with_source: bool,
+) -> Result<RecordBatch, ArrowError> {
+    let text = |get: fn(&DeliveryOrder) -> &String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(
+            orders.iter().map(|v| get(v).as_str()),
+        ))
+    };
+    let int = |get: fn(&DeliveryOrder) -> Option<Decimal>| -> ArrayRef {
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| get(v).and_then(|v| v.trunc().to_i64()))
+                .collect::<Int64Array>(),
+        )
+    };
+    let float = |get: fn(&DeliveryOrder) -> Option<Decimal>| -> ArrayRef {
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| get(v).and_then(|v| v.to_f64()))
+                .collect::<Float64Array>(),
+        )
+    };
+
+    let mut columns = vec![
+        text(DeliveryOrder::get_date),
+        text(DeliveryOrder::get_code),
+        text(DeliveryOrder::get_name),
+        text(DeliveryOrder::get_kind),
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| v.get_count().to_i64())
+                .collect::<Int64Array>(),
+        ),
+        float(DeliveryOrder::get_prize),
+        float(DeliveryOrder::get_amount),
+        int(DeliveryOrder::get_owned),
+        text(DeliveryOrder::get_account),
+    ];
+
+    if with_source {
+        columns.push(text(DeliveryOrder::get_source));
+        columns.push(Arc::new(UInt64Array::from_iter_values(
+            orders.iter().map(|v| v.get_line() as u64),
+        )));
+    }
+    RecordBatch::try_new(Arc::new(schema(with_source)), columns)
+}
+
+/// Write the orders into an Arrow IPC file, which can be mmap-loaded by Polars/Pandas.
+pub fn write_orders(
+    path: &str,
+    orders: &[DeliveryOrder],
+    with_source: bool,
+) -> Result<(), ArrowError> {
+    let batch = to_record_batch(orders, with_source)?;
+    let file = File::create(path)?;
+    let mut writer = FileWriter::try_new(file, batch.schema().as_ref())?;
+
+    writer.write(&batch)?;
+    writer.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use encoding_rs::GBK;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use crate::error::{Error, ParseError};
+use crate::number;
+use crate::reader;
+use crate::tokenizer::Dialect;
+use crate::DeliveryOrder;
+
+#[derive(Debug, Default, Clone)]
+pub struct Holding {
+    account: String,
+    code: String,
+    name: String,
+    count: i64,
+    cost: Decimal,
+}
+
+impl Holding {
+    pub fn get_account(&self) -> &String {
+        &self.account
+    }
+
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_count(&self) -> i64 {
+        self.count
+    }
+
+    /// Total cost of current holdings.
+    pub fn get_cost(&self) -> Decimal {
+        self.cost
+    }
+
+    pub fn get_average_cost(&self) -> Decimal {
+        if self.count != 0 {
+            self.cost / Decimal::from(self.count)
+        } else {
+            Decimal::ZERO
+        }
+    }
+
+    fn add(&mut self, order: &DeliveryOrder) {
+        let count = order.get_count().trunc().to_i64().unwrap_or(0);
+        let amount = order.get_amount().unwrap_or_default().abs();
+
+        if count > 0 {
+            // paid amount is the cost, or valued by the price if there is no amount
+            self.cost += if !amount.is_zero() {
+                amount
+            } else {
+                Decimal::from(count) * order.get_prize().unwrap_or_default()
+            };
+        } else if count < 0 {
+            // the cost reduce by the average cost
+            self.cost -= self.get_average_cost() * Decimal::from(count.abs());
+        }
+        self.name = order.get_name().clone();
+        self.count = order
+            .get_owned()
+            .and_then(|v| v.trunc().to_i64())
+            .unwrap_or(self.count + count);
+        if self.count == 0 {
+            self.cost = Decimal::ZERO;
+        }
+    }
+}
+
+/// Final holdings of each account at the end of processed period, the codes hold nothing are skipped.
+pub fn final_holdings(orders: &[DeliveryOrder]) -> Vec<Holding> {
+    let mut holdings: BTreeMap<(String, String), Holding> = BTreeMap::new();
+
+    for order in orders.iter().filter(|v| !v.get_code().is_empty()) {
+        holdings
+            .entry((order.get_account().clone(), order.get_code().clone()))
+            .or_insert_with(|| Holding {
+                account: order.get_account().clone(),
+                code: order.get_code().clone(),
+                ..Holding::default()
+            })
+            .add(order);
+    }
+
+    holdings.into_values().filter(|v| v.count != 0).collect()
+}
+
+/// Read the opening positions of `--initial-positions`, the rows are (account, code, count).
+///
+/// The columns are 证券代码, 持仓数量 and the optional 资金账号, the header is
+/// skipped if the quantity can't be parsed on the first line.
+pub fn read_positions(path: &str) -> Result<Vec<(String, String, i64)>, Error> {
+    let content = std::fs::read(path)?;
+    let (text, _, _) = reader::sniff(&content).unwrap_or(GBK).decode(&content);
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, v)| !v.trim().is_empty())
+        .collect();
+    let mut positions = vec![];
+    let dialect = match lines.first() {
+        Some((_, line)) => Dialect::detect(line),
+        None => return Ok(positions),
+    };
+
+    for (nth, (idx, line)) in lines.iter().enumerate() {
+        let columns = dialect.split(line);
+        let value = columns.get(1).map(|v| v.trim()).unwrap_or_default();
+
+        match number::parse_number(value).and_then(|v| v.trunc().to_i64()) {
+            Some(count) => positions.push((
+                columns
+                    .get(2)
+                    .map(|v| v.trim().to_owned())
+                    .unwrap_or_default(),
+                columns[0].trim().to_owned(),
+                count,
+            )),
+            None if nth == 0 => {}
+            None => {
+                return Err(Error::Malformed {
+                    path: path.to_owned(),
+                    line: idx + 1,
+                    source: ParseError::Number {
+                        column: "持仓数量".to_owned(),
+                        value: value.to_owned(),
+                    },
+                })
+            }
+        }
+    }
+
+    Ok(positions)
+}
@@ -1,16 +1,212 @@
 use async_std::channel::Sender;
 use async_std::fs::File;
-use async_std::io::{prelude::BufReadExt, BufReader};
+use async_std::io::{Cursor, Read};
 use async_std::sync::{Arc, Mutex};
-use encoding_rs::GBK;
+use chrono::{Duration, NaiveDate};
+use colored::Colorize;
+use encoding_rs::Encoding;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use crate::audit::{Audit, Level};
+use crate::broker::BrokerParser;
+use crate::date;
+use crate::error::{Error, Malformed, ParseError};
+use crate::mapping::{Holdings, Sign, TradeMap};
+use crate::number;
+use crate::reader::{self, LineReader, Lines};
+use crate::sheet;
+use crate::tokenizer::Dialect;
+use crate::zipped;
 use crate::{DeliveryOrder, Trade};
 
+const HEADER_KEYS: [&'static str; 3] = ["证券代码", "发生日期", "成交日期"];
+const FOOTER_KEYS: [&'static str; 6] = ["合计", "总计", "小计", "免责", "声明", "说明"];
+// 100.000
+const BOND_PAR_VALUE: Decimal = Decimal::from_parts(100_000, 0, 0, false, 3);
+const DATE_KEYS: [&'static str; 2] = ["发生日期", "日期"];
+const FEE_KEYS: [&'static str; 6] = [
+    "手续费",
+    "佣金",
+    "印花税",
+    "过户费",
+    "其他杂费",
+    "交易所清算费",
+];
+
+/// The delivery order exported by 华泰证券, the columns are understood by [`Context`] as is.
+#[derive(Debug)]
+pub struct Htsc;
+
+impl BrokerParser for Htsc {
+    fn get_name(&self) -> &'static str {
+        crate::HTSC_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        Context::is_header(line)
+    }
+}
+
+/// Date range covered by the rows of an account in an input file.
+#[derive(Debug, Clone)]
+pub struct Period {
+    path: String,
+    broker: String,
+    account: String,
+    first: NaiveDate,
+    last: NaiveDate,
+}
+
+impl Period {
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn get_broker(&self) -> &String {
+        &self.broker
+    }
+
+    pub fn get_account(&self) -> &String {
+        &self.account
+    }
+
+    pub fn get_first(&self) -> &NaiveDate {
+        &self.first
+    }
+
+    pub fn get_last(&self) -> &NaiveDate {
+        &self.last
+    }
+}
+
+/// Where the 证券余额 column of output comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OwnedSource {
+    /// copy the value of statement, the computed count is only for cross-checking
+    Reported,
+    /// accumulate the quantities
+    Computed,
+}
+
+impl Default for OwnedSource {
+    fn default() -> Self {
+        OwnedSource::Computed
+    }
+}
+
+impl OwnedSource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "reported" => Some(OwnedSource::Reported),
+            "computed" => Some(OwnedSource::Computed),
+            _ => None,
+        }
+    }
+}
+
+/// Where the computed balances start over from zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BalanceScope {
+    /// accumulate across all the inputs
+    Global,
+    /// each input file is an unrelated account
+    PerFile,
+    /// each calendar year is a disjoint period
+    PerYear,
+}
+
+impl Default for BalanceScope {
+    fn default() -> Self {
+        BalanceScope::Global
+    }
+}
+
+impl BalanceScope {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "global" => Some(BalanceScope::Global),
+            "per-file" => Some(BalanceScope::PerFile),
+            "per-year" => Some(BalanceScope::PerYear),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with the malformed lines, such as the numbers can't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnError {
+    /// stop at the first malformed line
+    Abort,
+    /// drop the line, only the count is reported
+    Skip,
+    /// drop the line and list it in the output
+    Report,
+}
+
+impl OnError {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "abort" => Some(OnError::Abort),
+            "skip" => Some(OnError::Skip),
+            "report" => Some(OnError::Report),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Context {
-    count: HashMap<String, i64>,
+    // (account, code) => computed holdings, the accounts of merged files are kept apart
+    count: HashMap<(String, String), i64>,
+    // (account, code) => date of the first row which computed count diverge from 证券余额
+    diverged: HashMap<(String, String), String>,
+    // (account, code) which computed count is negative now
+    negative: HashSet<(String, String)>,
+    dialect: Dialect,
+    // fixed-width columns supplied by user, otherwise detect from header
+    widths: Option<Vec<usize>>,
+    // column schema of the files without header
+    columns: Option<Vec<String>>,
+    // tolerance of 发生金额 validation
+    tolerance: f64,
+    trades: TradeMap,
+    // date ranges of input files, in processing order
+    periods: Vec<Period>,
+    // process the files in the order of command line
+    keep_order: bool,
+    // broker name => column => shares per unit, for the columns reported in lots (手)
+    units: HashMap<String, HashMap<String, i64>>,
+    // column name in statement => the column name understood
+    aliases: HashMap<String, String>,
+    // broker name => sign convention of its statements
+    signs: HashMap<String, Sign>,
+    owned_source: OwnedSource,
+    balance_scope: BalanceScope,
+    // drop the rows seen in other files, for the overlapping statements
+    dedup: bool,
+    // (date, code, kind, count, amount, 成交编号) => file of the row
+    seen: HashMap<(String, String, String, i64, Option<Decimal>, String), String>,
+    duplicates: usize,
+    // file or year of current balances, the balances are cleared when it changes
+    scope: Option<String>,
+    // stop after converting so many rows per file
+    limit: Option<usize>,
+    audit: Audit,
+    // broker of current file
+    broker: Arc<dyn BrokerParser>,
+    // not set: pad or truncate the columns, and log the malformed lines in audit
+    on_error: Option<OnError>,
+    malformed: Vec<Malformed>,
+    // 业务名称 not in mapping, the rows are ignored
+    unknown: BTreeSet<String>,
+    // encoding given by user, otherwise guess from content of each file
+    encoding: Option<&'static Encoding>,
+    // current file and line number, for audit log
+    path: String,
+    line_no: usize,
     debug: bool,
 }
 
@@ -18,16 +214,81 @@ impl Context {
     pub fn new() -> Self {
         Self {
             count: HashMap::default(),
+            diverged: HashMap::default(),
+            negative: HashSet::default(),
+            dialect: Dialect::default(),
+            widths: None,
+            columns: None,
+            tolerance: 0.01,
+            trades: TradeMap::new(),
+            periods: vec![],
+            keep_order: false,
+            units: HashMap::default(),
+            aliases: HashMap::default(),
+            signs: HashMap::default(),
+            owned_source: OwnedSource::default(),
+            balance_scope: BalanceScope::default(),
+            dedup: false,
+            seen: HashMap::default(),
+            duplicates: 0,
+            scope: None,
+            limit: None,
+            audit: Audit::new(),
+            broker: Arc::new(Htsc),
+            on_error: None,
+            malformed: vec![],
+            unknown: BTreeSet::default(),
+            encoding: None,
+            path: String::default(),
+            line_no: 0,
             debug: false,
         }
     }
 
-    pub fn has(&self, key: &str) -> bool {
-        self.count.contains_key(key)
+    /// A context of the same settings for extracting in parallel, the balances start
+    /// from current ones and the findings are empty.
+    pub fn fork(&self) -> Self {
+        Self {
+            count: self.count.clone(),
+            widths: self.widths.clone(),
+            columns: self.columns.clone(),
+            tolerance: self.tolerance,
+            trades: self.trades.clone(),
+            keep_order: self.keep_order,
+            units: self.units.clone(),
+            aliases: self.aliases.clone(),
+            signs: self.signs.clone(),
+            owned_source: self.owned_source,
+            balance_scope: self.balance_scope,
+            dedup: self.dedup,
+            limit: self.limit,
+            broker: self.broker.clone(),
+            on_error: self.on_error,
+            encoding: self.encoding,
+            debug: self.debug,
+            ..Self::new()
+        }
+    }
+
+    /// Take the findings of `other` forked by [`Context::fork`], such as the audit log.
+    pub fn join(&mut self, other: Context) {
+        for (key, date) in other.diverged {
+            self.diverged.entry(key).or_insert(date);
+        }
+        self.periods.extend(other.periods);
+        self.duplicates += other.duplicates;
+        self.audit.extend(other.audit);
+        self.malformed.extend(other.malformed);
+        self.unknown.extend(other.unknown);
+    }
+
+    pub fn has(&self, account: &str, code: &str) -> bool {
+        self.count
+            .contains_key(&(account.to_owned(), code.to_owned()))
     }
 
-    pub fn get_count(&self, key: &str) -> Option<&i64> {
-        self.count.get(key)
+    pub fn get_count(&self, account: &str, code: &str) -> Option<&i64> {
+        self.count.get(&(account.to_owned(), code.to_owned()))
     }
 
     pub fn set_debug(&mut self, debug: bool) -> &mut Self {
@@ -35,8 +296,394 @@ impl Context {
         self
     }
 
-    pub fn add_count(&mut self, key: String, count: i64) {
-        *self.count.entry(key).or_insert(0) += count;
+    pub fn set_widths(&mut self, widths: Option<Vec<usize>>) -> &mut Self {
+        self.widths = widths;
+        self
+    }
+
+    pub fn set_columns(&mut self, columns: Option<Vec<String>>) -> &mut Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f64) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn get_trade_map(&self) -> &TradeMap {
+        &self.trades
+    }
+
+    pub fn get_trade_map_mut(&mut self) -> &mut TradeMap {
+        &mut self.trades
+    }
+
+    pub fn get_audit(&self) -> &Audit {
+        &self.audit
+    }
+
+    pub fn add_audit(&mut self, level: Level, message: String) {
+        self.audit.add(level, &self.path, self.line_no, message);
+    }
+
+    /// Set the line number of current file, for the files read by [`BrokerParser::read_file`].
+    pub fn set_line_no(&mut self, line_no: usize) -> &mut Self {
+        self.line_no = line_no;
+        self
+    }
+
+    pub fn set_keep_order(&mut self, keep_order: bool) -> &mut Self {
+        self.keep_order = keep_order;
+        self
+    }
+
+    pub fn get_keep_order(&self) -> bool {
+        self.keep_order
+    }
+
+    pub fn set_units(&mut self, units: HashMap<String, HashMap<String, i64>>) -> &mut Self {
+        self.units = units;
+        self
+    }
+
+    pub fn set_aliases(&mut self, aliases: HashMap<String, String>) -> &mut Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Shares per unit of `column` of current broker, 1 if the column is reported in shares.
+    pub fn unit_of(&self, column: &str) -> i64 {
+        self.units
+            .get(self.broker.get_name())
+            .and_then(|v| v.get(column))
+            .cloned()
+            .unwrap_or(1)
+    }
+
+    pub fn set_signs(&mut self, signs: HashMap<String, Sign>) -> &mut Self {
+        self.signs = signs;
+        self
+    }
+
+    /// Sign convention of current broker, the one declared by the broker if not configured.
+    pub fn sign_of(&self) -> Sign {
+        self.signs
+            .get(self.broker.get_name())
+            .cloned()
+            .unwrap_or(self.broker.get_sign())
+    }
+
+    pub fn set_owned_source(&mut self, owned_source: OwnedSource) -> &mut Self {
+        self.owned_source = owned_source;
+        self
+    }
+
+    pub fn set_balance_scope(&mut self, balance_scope: BalanceScope) -> &mut Self {
+        self.balance_scope = balance_scope;
+        self
+    }
+
+    pub fn set_dedup(&mut self, dedup: bool) -> &mut Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Count of the rows dropped by `--dedup`.
+    pub fn get_duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Return true if `order` is seen in another file.
+    ///
+    /// The same rows in one file are separate fills, only the rows repeated by
+    /// overlapping statements are duplicates.
+    fn is_duplicate(&mut self, order: &DeliveryOrder, count: i64) -> bool {
+        let key = (
+            order.get_date().clone(),
+            order.get_code().clone(),
+            order.get_kind().clone(),
+            count,
+            order.get_amount(),
+            order.get_trade_no().clone(),
+        );
+
+        match self.seen.get(&key) {
+            Some(path) if path != &self.path => {
+                self.duplicates += 1;
+                self.add_audit(
+                    Level::Info,
+                    format!(
+                        "drop duplicate of {}: {} {} @date<{}>",
+                        path,
+                        order.get_code(),
+                        order.get_kind(),
+                        order.get_date()
+                    ),
+                );
+                true
+            }
+            Some(_) => false,
+            None => {
+                self.seen.insert(key, self.path.clone());
+                false
+            }
+        }
+    }
+
+    /// Clear the balances if `order` starts a new file or year of [`BalanceScope`].
+    ///
+    /// The balances of first scope are kept, they may be seeded by `--initial-positions`.
+    fn enter_scope(&mut self, order: &DeliveryOrder) {
+        let scope = match self.balance_scope {
+            BalanceScope::Global => return,
+            BalanceScope::PerFile => self.path.clone(),
+            BalanceScope::PerYear => match date::parse_date(order.get_date()) {
+                Some(date) => date.format("%Y").to_string(),
+                None => return,
+            },
+        };
+
+        match &self.scope {
+            Some(current) if current == &scope => {}
+            Some(_) => {
+                self.count.clear();
+                self.negative.clear();
+                self.scope = Some(scope);
+            }
+            None => self.scope = Some(scope),
+        }
+    }
+
+    pub fn set_broker(&mut self, broker: Arc<dyn BrokerParser>) -> &mut Self {
+        self.broker = broker;
+        self
+    }
+
+    pub fn set_limit(&mut self, limit: Option<usize>) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn get_limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    pub fn set_encoding(&mut self, encoding: Option<&'static Encoding>) -> &mut Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Open the lines of file, the rows of spreadsheet are joined by tab.
+    ///
+    /// The text file, entry of zip file or standard input is decoded with the encoding given
+    /// by user, or guessed from the content, fall back to the encoding of broker.
+    async fn open(&self, path: &str) -> std::io::Result<Lines> {
+        if sheet::is_sheet_path(path) {
+            let rows = sheet::read_lines(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+            return Ok(Lines::Rows(rows.into_iter()));
+        }
+        let (inner, sniffed): (Box<dyn Read + Unpin + Send>, _) = if path == reader::STDIN_PATH {
+            let content = reader::read_stdin()?;
+
+            (Box::new(Cursor::new(content)), reader::sniff(content))
+        } else if zipped::split_entry(path).is_some() {
+            let content = zipped::read_entry(path)?;
+            let sniffed = reader::sniff(&content);
+
+            (Box::new(Cursor::new(content)), sniffed)
+        } else {
+            (
+                Box::new(File::open(path).await?),
+                reader::sniff_file(path).await?,
+            )
+        };
+        let encoding = self
+            .encoding
+            .or(sniffed)
+            .unwrap_or(self.broker.get_encoding());
+
+        if self.debug {
+            eprintln!("read file {} as {}", path, encoding.name());
+        }
+        Ok(Lines::Text(LineReader::new(inner, encoding)))
+    }
+
+    /// (account, code) which computed count diverge from 证券余额, with the date of first divergence.
+    pub fn get_diverged(&self) -> &HashMap<(String, String), String> {
+        &self.diverged
+    }
+
+    pub fn set_on_error(&mut self, on_error: Option<OnError>) -> &mut Self {
+        self.on_error = on_error;
+        self
+    }
+
+    pub fn get_on_error(&self) -> Option<OnError> {
+        self.on_error
+    }
+
+    pub fn get_malformed(&self) -> &Vec<Malformed> {
+        &self.malformed
+    }
+
+    /// The 业务名称 not in mapping, which rows are ignored.
+    pub fn get_unknown(&self) -> &BTreeSet<String> {
+        &self.unknown
+    }
+
+    /// Parse the price or amount, the malformed number is an error only if `--on-error` given.
+    fn parse_number(&self, column: &str, value: &str) -> Result<Option<Decimal>, ParseError> {
+        let number = number::parse_number(value);
+
+        if self.on_error.is_some() && !value.is_empty() && number.is_none() {
+            return Err(ParseError::Number {
+                column: column.to_owned(),
+                value: value.to_owned(),
+            });
+        }
+        Ok(number)
+    }
+
+    /// Handle the line can't be converted, return error if it should stop the file.
+    pub fn handle_malformed(&mut self, error: ParseError, content: &str) -> Result<(), Error> {
+        match self.on_error {
+            None => {
+                self.add_audit(Level::Error, format!("{}: {}", error, content));
+            }
+            Some(OnError::Abort) => {
+                // kept for the final report, the run stops after the files are closed
+                self.malformed.push(Malformed::new(
+                    self.path.clone(),
+                    self.line_no,
+                    error.clone(),
+                    content.to_owned(),
+                ));
+                return Err(Error::Malformed {
+                    path: self.path.clone(),
+                    line: self.line_no,
+                    source: error,
+                });
+            }
+            Some(OnError::Skip) | Some(OnError::Report) => {
+                self.malformed.push(Malformed::new(
+                    self.path.clone(),
+                    self.line_no,
+                    error,
+                    content.to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_periods(&self) -> &Vec<Period> {
+        &self.periods
+    }
+
+    fn update_period(&mut self, order: &DeliveryOrder) {
+        if let Some(date) = date::parse_date(order.get_date()) {
+            let broker = self.broker.get_name();
+            let period = self.periods.iter_mut().rev().find(|v| {
+                v.path == self.path && v.broker == broker && &v.account == order.get_account()
+            });
+
+            match period {
+                Some(period) => {
+                    period.first = period.first.min(date);
+                    period.last = period.last.max(date);
+                }
+                None => {
+                    self.periods.push(Period {
+                        path: self.path.clone(),
+                        broker: broker.to_owned(),
+                        account: order.get_account().clone(),
+                        first: date,
+                        last: date,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The periods of each (broker, account), sorted by the first date.
+    ///
+    /// The statements of different accounts are not continuous with each other.
+    fn grouped_periods(&self) -> BTreeMap<(&String, &String), Vec<&Period>> {
+        let mut groups: BTreeMap<(&String, &String), Vec<&Period>> = BTreeMap::new();
+
+        for period in self.periods.iter() {
+            groups
+                .entry((&period.broker, &period.account))
+                .or_default()
+                .push(period);
+        }
+        for periods in groups.values_mut() {
+            periods.sort_by_key(|v| (v.first, v.last));
+        }
+        groups
+    }
+
+    /// Warn about the whole months missed between consecutive input files of an account.
+    pub fn check_gaps(&mut self) {
+        let mut gaps = vec![];
+
+        for pair in self
+            .grouped_periods()
+            .values()
+            .flat_map(|periods| periods.windows(2))
+        {
+            let (prev, next) = (pair[0], pair[1]);
+
+            if date::month_of(&next.first) - date::month_of(&prev.last) >= 2 {
+                gaps.push((
+                    next.path.clone(),
+                    format!(
+                        "missing date range {} ~ {} between {} and {}",
+                        prev.last + Duration::days(1),
+                        next.first - Duration::days(1),
+                        prev.path,
+                        next.path
+                    ),
+                ));
+            }
+        }
+        for (path, message) in gaps {
+            self.audit.add(Level::Warning, &path, 0, message);
+        }
+    }
+
+    /// Warn about the input files of an account cover overlapping dates, the rows in it may be duplicated.
+    pub fn check_overlaps(&mut self) {
+        let mut overlaps = vec![];
+
+        for periods in self.grouped_periods().values() {
+            for (idx, period) in periods.iter().enumerate() {
+                for other in periods.iter().skip(idx + 1) {
+                    if period.first <= other.last && other.first <= period.last {
+                        overlaps.push((
+                        other.path.clone(),
+                        format!(
+                            "date range {} ~ {} covered by both {} and {}, rows in it may be duplicated",
+                            period.first.max(other.first),
+                            period.last.min(other.last),
+                            period.path,
+                            other.path
+                        ),
+                    ));
+                    }
+                }
+            }
+        }
+        for (path, message) in overlaps {
+            self.audit.add(Level::Warning, &path, 0, message);
+        }
+    }
+
+    pub fn add_count(&mut self, account: String, code: String, count: i64) {
+        *self.count.entry((account, code)).or_insert(0) += count;
     }
 
     pub fn gen_title() -> Vec<String> {
@@ -54,17 +701,75 @@ impl Context {
         .to_vec()
     }
 
-    pub fn gen_order(&mut self, titles: &Vec<String>, line: String) -> DeliveryOrder {
-        let columns: Vec<&str> = line.trim().split("\t").collect();
+    pub fn gen_order(
+        &mut self,
+        titles: &Vec<String>,
+        line: String,
+    ) -> Result<DeliveryOrder, ParseError> {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let columns = self.dialect.split(line);
+
+        self.gen_order_from_fields(titles, columns, line)
+    }
 
-        assert_eq!(columns.len(), titles.len());
+    fn gen_order_from_fields(
+        &mut self,
+        titles: &Vec<String>,
+        mut columns: Vec<String>,
+        line: &str,
+    ) -> Result<DeliveryOrder, ParseError> {
+        if columns.len() != titles.len() && self.on_error.is_some() {
+            return Err(ParseError::Columns {
+                expect: titles.len(),
+                got: columns.len(),
+            });
+        }
+        if columns.len() != titles.len() {
+            self.add_audit(
+                Level::Warning,
+                format!(
+                    "expect {} columns but got {}, {} => {}",
+                    titles.len(),
+                    columns.len(),
+                    if columns.len() > titles.len() {
+                        "truncate"
+                    } else {
+                        "pad"
+                    },
+                    line
+                ),
+            );
+            columns.resize(titles.len(), String::new());
+        }
 
-        let mut delivery_order = DeliveryOrder::default();
+        let mut delivery_order = DeliveryOrder::default()
+            .with_source(self.path.clone())
+            .with_line(self.line_no);
         let mut count = 0;
         let mut left_count = None;
+        let mut fees = Decimal::ZERO;
+        let mut has_fee = false;
+        let rule = self
+            .trades
+            .match_rule(|field| {
+                titles
+                    .iter()
+                    .position(|v| v == field)
+                    .map(|idx| columns[idx].trim())
+            })
+            .cloned();
 
-        for (title, &column) in titles.iter().zip(columns.iter()) {
-            let value = column.trim().to_owned();
+        if let Some(rule) = &rule {
+            let category = self.trades.category(rule.get_trade());
+
+            delivery_order = delivery_order
+                .with_kind(category.get_label().clone())
+                .with_trade(rule.get_trade().clone());
+        }
+        for (title, column) in titles.iter().zip(columns.iter()) {
+            let value = self.broker.map_value(title, column.trim());
+            let column = value.as_str();
+            let value = column.to_owned();
 
             match title.as_str() {
                 "发生日期" | "日期" => {
@@ -76,151 +781,619 @@ impl Context {
                 "证券名称" | "股票名称" => {
                     delivery_order = delivery_order.with_name(value);
                 }
+                "资金账号" | "股东代码" | "股东账户" => {
+                    // prefer the 资金账号 if both exist
+                    if title == "资金账号" || delivery_order.get_account().is_empty() {
+                        delivery_order = delivery_order.with_account(value);
+                    }
+                }
                 "成交数量" | "发生数量" => {
-                    count = value
-                        .parse::<f64>()
-                        .expect(&format!("Can not parse {} as i64", column))
-                        as i64;
-                    count = count.abs();
+                    if !value.is_empty() {
+                        count = number::parse_number(&value)
+                            .and_then(|v| v.trunc().to_i64())
+                            .ok_or_else(|| ParseError::Number {
+                                column: title.clone(),
+                                value: value.clone(),
+                            })?
+                            * self.unit_of(title);
+                    }
                 }
                 "成交价格" | "成交均价" => {
-                    delivery_order = delivery_order.with_prize(value);
+                    delivery_order = delivery_order.with_prize(self.parse_number(title, &value)?);
                 }
                 "发生金额" | "收付金额" => {
-                    delivery_order = delivery_order.with_amount(value);
+                    delivery_order = delivery_order.with_amount(self.parse_number(title, &value)?);
                 }
-                "业务名称" | "业务标志" => {
-                    let value = match column {
-                        "证券卖出" => {
-                            delivery_order = delivery_order.with_trade(crate::Trade::Sell);
-                            "卖出"
-                        }
-                        "证券买入" | "开放基金认购结果" => {
-                            delivery_order = delivery_order.with_trade(crate::Trade::Buy);
-                            "买入"
-                        }
-                        "银证转存" | "银行转存" | "利息归本" => {
-                            delivery_order = delivery_order.with_trade(crate::Trade::In);
-                            "银证转入"
-                        }
-                        "银证转取" | "银行转取" => {
-                            delivery_order = delivery_order.with_trade(crate::Trade::Out);
-                            "银证转出"
-                        }
-                        _ => {
-                            delivery_order = delivery_order.with_trade(crate::Trade::Ignore);
-                            continue;
+                "业务名称" | "业务标志" if rule.is_none() => {
+                    let trade = self.trades.get(column);
+
+                    if !self.trades.contains(column) {
+                        self.unknown.insert(column.to_owned());
+                        self.add_audit(
+                            Level::Warning,
+                            format!("unknown 业务名称 {}, row ignored", column),
+                        );
+                    }
+                    delivery_order.set_margin(self.trades.is_margin(column));
+                    if trade == Trade::Ignore {
+                        delivery_order = delivery_order.with_trade(Trade::Ignore);
+                        continue;
+                    }
+                    let category = self.trades.category(&trade);
+
+                    delivery_order = delivery_order
+                        .with_kind(category.get_label().clone())
+                        .with_trade(trade);
+                }
+                title if FEE_KEYS.contains(&title) => {
+                    let fee = self.parse_number(title, &value)?;
+
+                    fees += fee.unwrap_or_default();
+                    has_fee = true;
+                    match title {
+                        "佣金" => delivery_order.set_commission(fee),
+                        "手续费" if delivery_order.get_commission().is_none() => {
+                            delivery_order.set_commission(fee)
                         }
-                    };
-                    delivery_order = delivery_order.with_kind(value.to_owned());
+                        "印花税" => delivery_order.set_stamp_tax(fee),
+                        "过户费" => delivery_order.set_transfer_fee(fee),
+                        _ => {}
+                    }
+                }
+                "成交编号" | "成交序号" => {
+                    delivery_order.set_trade_no(value);
                 }
                 "证券数量" => {
-                    left_count = Some(value.parse::<f64>().unwrap() as i64);
+                    left_count = self
+                        .parse_number(title, &value)?
+                        .and_then(|v| v.trunc().to_i64())
+                        .map(|v| v * self.unit_of(title));
                 }
                 _ => {}
             }
         }
-        if delivery_order.get_trade() == &Trade::Sell {
-            count = -count;
+        if has_fee {
+            delivery_order.set_fee(Some(number::round(fees, 2)));
+        }
+        if delivery_order.get_prize().unwrap_or_default().is_zero()
+            && delivery_order.get_trade() == &Trade::Custom("配债入账".to_owned())
+        {
+            // convertible bonds are allotted at par value
+            delivery_order.set_prize(Some(BOND_PAR_VALUE));
+        }
+        if let Some(sign) = rule.as_ref().and_then(|v| v.get_sign()) {
+            if let Some(amount) = delivery_order.get_amount() {
+                delivery_order.set_amount(Some(amount.abs() * Decimal::from(sign.signum())));
+            }
+        } else {
+            let sign = self.sign_of();
+            let category = self.trades.category(delivery_order.get_trade());
+
+            if let Some(amount) = delivery_order.get_amount() {
+                delivery_order.set_amount(Some(sign.apply(
+                    delivery_order.get_trade(),
+                    &category,
+                    amount,
+                )));
+            }
+        }
+        if delivery_order.get_amount().is_none() {
+            self.derive_amount(&mut delivery_order, count.abs(), fees);
+        } else {
+            self.validate_amount(&delivery_order, count.abs(), fees);
+        }
+
+        if self.dedup && self.is_duplicate(&delivery_order, count) {
+            return Ok(delivery_order.with_trade(Trade::Ignore));
         }
-        self.add_count(delivery_order.get_code().clone(), count);
-        delivery_order = delivery_order.with_count(count.to_string());
-        if let Some(count) = self.get_count(delivery_order.get_code()) {
+        self.enter_scope(&delivery_order);
+        let category = self.trades.category(delivery_order.get_trade());
+        let holdings = rule
+            .as_ref()
+            .and_then(|v| v.get_holdings())
+            .unwrap_or(category.get_holdings());
+
+        count = holdings.apply(count);
+        // the quantity of 红股入账 and 除权 rows may be missing, the change of 证券余额 is recorded
+        if count == 0 && holdings != Holdings::None && !category.is_cash() {
             if let Some(left_count) = left_count {
-                if left_count != *count {
-                    println!(
-                        "Count not equal: {} <-> {} @date<{}>",
-                        left_count,
-                        count,
-                        delivery_order.get_date(),
+                let current = self
+                    .get_count(delivery_order.get_account(), delivery_order.get_code())
+                    .cloned()
+                    .unwrap_or(0);
+
+                count = left_count - current;
+                if count != 0 {
+                    self.add_audit(
+                        Level::Info,
+                        format!(
+                            "derived quantity {} of {} from 证券余额 {} @date<{}>",
+                            count,
+                            delivery_order.get_code(),
+                            left_count,
+                            delivery_order.get_date()
+                        ),
                     );
                 }
             }
-            delivery_order = delivery_order.with_owned(format!("{}", count));
+        }
+        self.add_count(
+            delivery_order.get_account().clone(),
+            delivery_order.get_code().clone(),
+            count,
+        );
+        delivery_order = delivery_order.with_count(Decimal::from(count));
+        if let Some(count) = self
+            .get_count(delivery_order.get_account(), delivery_order.get_code())
+            .cloned()
+        {
+            self.check_negative(&delivery_order, count);
+            if let Some(left_count) = left_count {
+                if left_count != count {
+                    self.report_divergence(&delivery_order, left_count, count);
+                }
+            }
+            delivery_order = delivery_order.with_owned(Some(Decimal::from(count)));
+        }
+        if self.owned_source == OwnedSource::Reported {
+            if let Some(left_count) = left_count {
+                delivery_order = delivery_order.with_owned(Some(Decimal::from(left_count)));
+            }
+        }
+
+        Ok(delivery_order)
+    }
+
+    /// Return quantity × price ± fees signed by trade direction, buy and sell only.
+    fn expected_amount(order: &DeliveryOrder, count: i64, fees: Decimal) -> Option<Decimal> {
+        let prize = order.get_prize()?;
+
+        match order.get_trade() {
+            Trade::Buy => Some(-(Decimal::from(count) * prize + fees)),
+            Trade::Sell => Some(Decimal::from(count) * prize - fees),
+            _ => None,
+        }
+    }
+
+    /// Derive 发生金额 from quantity, price and fees.
+    fn derive_amount(&mut self, order: &mut DeliveryOrder, count: i64, fees: Decimal) {
+        if let Some(amount) = Self::expected_amount(order, count, fees) {
+            let amount = number::round(amount, 2);
+
+            order.set_amount(Some(amount));
+            self.add_audit(
+                Level::Info,
+                format!(
+                    "derived 发生金额 {:.2} = {} x {} ± {:.2} for {} @date<{}>",
+                    amount,
+                    count,
+                    number::format_number(order.get_prize()),
+                    fees,
+                    order.get_code(),
+                    order.get_date()
+                ),
+            );
+        }
+    }
+
+    /// Check the 发生金额 add up, a mismatch usually means a mis-mapped column.
+    fn validate_amount(&mut self, order: &DeliveryOrder, count: i64, fees: Decimal) {
+        let amount = match order.get_amount() {
+            Some(amount) => amount,
+            None => return,
+        };
+        let tolerance = Decimal::from_f64(self.tolerance).unwrap_or_default();
+
+        if let Some(expected) = Self::expected_amount(order, count, fees) {
+            if (expected - amount).abs() > tolerance {
+                self.add_audit(
+                    Level::Warning,
+                    format!(
+                        "发生金额 {} not add up, expect {:.2} = {} x {} ± {:.2} for {} @date<{}>",
+                        amount,
+                        expected,
+                        count,
+                        number::format_number(order.get_prize()),
+                        fees,
+                        order.get_code(),
+                        order.get_date()
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Report the code once when its holdings go below zero, which is impossible for a cash account.
+    fn check_negative(&mut self, order: &DeliveryOrder, count: i64) {
+        let key = (order.get_account().clone(), order.get_code().clone());
+
+        if count >= 0 {
+            self.negative.remove(&key);
+        } else if self.negative.insert(key) {
+            self.add_audit(
+                Level::Error,
+                format!(
+                    "negative holdings of {}: {} @date<{}>, missing earlier statement or opening position?",
+                    order.get_code(),
+                    count,
+                    order.get_date()
+                ),
+            );
+            eprintln!(
+                "{} {} {} {} @date<{}>",
+                "negative holdings:".bold().red(),
+                order.get_code(),
+                order.get_name(),
+                count,
+                order.get_date(),
+            );
+        }
+    }
+
+    fn report_divergence(&mut self, order: &DeliveryOrder, expected: i64, computed: i64) {
+        let first = self
+            .diverged
+            .entry((order.get_account().clone(), order.get_code().clone()))
+            .or_insert(order.get_date().clone())
+            .clone();
+        let missed = expected - computed;
+
+        self.add_audit(
+            Level::Warning,
+            format!(
+                "证券余额 mismatch of {}: expected {} but computed {}, first divergence @date<{}>",
+                order.get_code(),
+                expected,
+                computed,
+                first
+            ),
+        );
+
+        eprintln!(
+            "{} {} {} @date<{}>",
+            "证券余额 mismatch:".bold().yellow(),
+            order.get_code(),
+            order.get_name(),
+            order.get_date(),
+        );
+        eprintln!("{}", format!("- expected (statement) : {}", expected).red());
+        eprintln!(
+            "{}",
+            format!("+ computed             : {}", computed).green()
+        );
+        eprintln!(
+            "  first divergence     : {}",
+            format!("{}, {:+} shares unaccounted before it", first, missed).bold()
+        );
+    }
+
+    pub fn is_header(line: &str) -> bool {
+        HEADER_KEYS.iter().any(|key| line.contains(key))
+    }
+
+    /// Detail rows must have a business column, the sections without it are summary.
+    pub fn is_summary(title: &Vec<String>) -> bool {
+        !title.iter().any(|v| v == "业务名称" || v == "业务标志")
+    }
+
+    /// Return `Some(true)` if the line start a 汇总 section, `Some(false)` for a 明细 section.
+    pub fn section_of(&self, line: &str) -> Option<bool> {
+        let fields = self
+            .dialect
+            .split(line)
+            .into_iter()
+            .filter(|v| !v.trim().is_empty())
+            .count();
+
+        if fields <= 1 {
+            if line.contains("汇总") {
+                return Some(true);
+            } else if line.contains("明细") {
+                return Some(false);
+            }
+        }
+        None
+    }
+
+    /// Totals and disclaimer text at the end of file, which has keywords like 合计.
+    ///
+    /// The rows not match the header are not footers, they are reported as malformed.
+    pub fn is_footer(&self, line: &str) -> bool {
+        FOOTER_KEYS.iter().any(|key| line.contains(key))
+    }
+
+    fn parse_title(&mut self, line: &str) -> Vec<String> {
+        self.dialect = match &self.widths {
+            Some(widths) => Dialect::fixed_width(widths.clone()),
+            None => Dialect::detect(line),
+        };
+        self.dialect
+            .split(line)
+            .into_iter()
+            .map(|v| {
+                let v = v.trim();
+
+                // the alias may name a column which the broker renames
+                self.broker
+                    .map_title(self.aliases.get(v).map(|v| v.as_str()).unwrap_or(v))
+            })
+            .collect()
+    }
+
+    /// Pre-scan the first and last date of the file.
+    pub async fn scan_period(
+        &mut self,
+        path: &str,
+    ) -> std::io::Result<Option<(NaiveDate, NaiveDate)>> {
+        let broker = self.broker.clone();
+        let orders = match broker.read_file(self, path) {
+            Some(read) => Some(read.await),
+            None => None,
+        };
+
+        // the file is read again when extracting
+        if let Some(orders) = orders {
+            let dates: Vec<NaiveDate> = orders
+                .map(|orders| {
+                    orders
+                        .iter()
+                        .filter_map(|v| date::parse_date(v.get_date()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            return Ok(dates.iter().min().cloned().zip(dates.iter().max().cloned()));
+        }
+        let mut reader = self.open(path).await?;
+        let mut title = self.columns.clone().unwrap_or_default();
+        let mut ret: Option<(NaiveDate, NaiveDate)> = None;
+
+        while let Some((_, line)) = reader.next_line().await? {
+            if self.broker.detect(&line) {
+                title = self.parse_title(&line);
+                continue;
+            }
+            let index = title.iter().position(|v| DATE_KEYS.contains(&v.as_str()));
+
+            if let Some(index) = index {
+                if let Some(date) = self
+                    .dialect
+                    .split(&line)
+                    .get(index)
+                    .and_then(|v| date::parse_date(v))
+                {
+                    ret = Some(match ret {
+                        Some((first, last)) => (first.min(date), last.max(date)),
+                        None => (date, date),
+                    });
+                }
+            }
         }
 
-        delivery_order
+        Ok(ret)
     }
 
     pub async fn extract_from_file(
         &mut self,
         path: String,
         sender: Arc<Sender<Option<DeliveryOrder>>>,
-    ) -> std::io::Result<()> {
-        if path.ends_with("txt") {
-            self.extract_from_file_impl(path, sender).await?;
-        } else {
-            panic!("Not support current file: {}", path);
+    ) -> Result<(), Error> {
+        self.path = path.clone();
+        self.line_no = 0;
+
+        let broker = self.broker.clone();
+        let orders = match broker.read_file(self, &path) {
+            Some(read) => Some(read.await),
+            None => None,
+        };
+        let ret = match orders {
+            Some(Ok(orders)) => {
+                self.send_orders(orders, &sender).await;
+                Ok(())
+            }
+            Some(Err(e)) => Err(e),
+            // the delimiter of CSV is detected from header as TXT, and
+            // the rows of spreadsheet are read as the lines separated by tab
+            None if path.ends_with("txt")
+                || path.ends_with("csv")
+                || path == reader::STDIN_PATH
+                || sheet::is_sheet_path(&path) =>
+            {
+                self.extract_from_file_impl(path.clone(), sender.clone())
+                    .await
+            }
+            None => Err(Error::Unsupported(path.clone())),
+        };
+
+        if let Err(e) = &ret {
+            self.add_audit(Level::Error, e.to_string());
+        }
+        // the end of file is sent anyway, the writer is waiting for it
+        sender
+            .send(None)
+            .await
+            .expect(&format!("Can't send data to write thread"));
+        if self.debug {
+            eprintln!("extract file {} is over!", &path);
+        }
+        ret
+    }
+
+    /// Send the orders read by [`BrokerParser::read_file`], at most `limit` rows.
+    async fn send_orders(
+        &mut self,
+        orders: Vec<DeliveryOrder>,
+        sender: &Sender<Option<DeliveryOrder>>,
+    ) {
+        for order in orders
+            .into_iter()
+            .filter(|v| v.is_valid())
+            .take(self.limit.unwrap_or(usize::MAX))
+        {
+            self.update_period(&order);
+            sender.send(Some(order)).await.expect(&format!(
+                "Can't send data from read thread: {}!",
+                &self.path
+            ));
         }
-        Ok(())
     }
 
     async fn extract_from_file_impl(
         &mut self,
         path: String,
         sender: Arc<Sender<Option<DeliveryOrder>>>,
-    ) -> std::io::Result<()> {
-        let mut reader = BufReader::new(File::open(&path).await?);
-        let mut buffer = Vec::with_capacity(4096);
-        let gbk_encoder = GBK;
+    ) -> Result<(), Error> {
+        let mut reader = self.open(&path).await?;
         let mut title: Vec<String> = vec![];
+        let mut summary = false;
+        let mut headed = false;
+        let mut converted = 0;
+        // a quoted field of CSV may span multiple lines
+        let csv = path.ends_with("csv");
+        let mut pending = String::new();
+        let mut start = 0;
 
-        if reader.read_until(0x0a as u8, &mut buffer).await? > 0 {
-            let (line, _, _) = gbk_encoder.decode(&buffer);
-            title = line.trim().split("\t").map(|v| String::from(v)).collect();
-            buffer.clear();
-        }
         if self.debug {
-            println!("start extract data from file: {:?}", &path);
+            eprintln!("start extract data from file: {:?}", &path);
         }
-        loop {
-            let size = reader.read_until(0x0a as u8, &mut buffer).await?;
+        self.path = path.clone();
+        while let Some((line_no, line)) = reader.next_line().await? {
+            if pending.is_empty() {
+                start = line_no;
+            } else {
+                pending.push('\n');
+            }
+            pending.push_str(&line);
+            if csv && pending.matches(self.dialect.get_quote()).count() % 2 == 1 {
+                continue;
+            }
+            let line = std::mem::take(&mut pending);
+            let line = line.as_str();
 
-            if size > 0 {
-                let (line, _, _) = gbk_encoder.decode(&buffer);
+            if self.limit.map(|v| converted >= v).unwrap_or(false) {
+                if self.debug {
+                    eprintln!("reach limit of file {}", &path);
+                }
+                break;
+            }
 
-                // if self.debug {
-                //     println!("read line => {}", line);
-                // }
-                let order = self.gen_order(&title, line.to_string());
+            self.line_no = start;
+
+            // if self.debug {
+            //     println!("read line => {}", line);
+            // }
+            if !headed && !line.trim().is_empty() && !self.broker.detect(line) {
+                if let Some(columns) = self.columns.clone() {
+                    if self.debug {
+                        eprintln!("file {} has no header, use the column schema", &path);
+                    }
+                    self.dialect = match &self.widths {
+                        Some(widths) => Dialect::fixed_width(widths.clone()),
+                        None => Dialect::detect(line),
+                    };
+                    title = columns;
+                }
+                headed = true;
+            }
+            if self.broker.detect(line) {
+                // header of a new section, or repeated header of per-month sub-tables
+                title = self.parse_title(line);
+                summary = Self::is_summary(&title);
+                headed = true;
+            } else if line.trim().is_empty() {
+                // skip blank lines, include the trailing blank lines
+            } else if let Some(is_summary) = self.section_of(line) {
+                summary = is_summary;
+            } else if summary || title.is_empty() {
+                // skip the rows not belong to detail section
+                self.add_audit(Level::Info, format!("skip line: {}", line));
+            } else if self.is_footer(line) {
+                if self.debug {
+                    eprintln!("reach footer of file {}: {}", &path, line);
+                }
+                self.add_audit(Level::Info, format!("skip footer line: {}", line));
+                // ignore rest lines until next header
+                title.clear();
+            } else {
+                let broker = self.broker.clone();
+                let order = match broker.parse_line(self, &title, line) {
+                    Ok(order) => order,
+                    Err(e) => {
+                        // go on with the rest lines unless abort
+                        self.handle_malformed(e, line)?;
+                        continue;
+                    }
+                };
+
+                self.update_period(&order);
 
                 if order.is_valid() {
                     sender
                         .send(Some(order))
                         .await
                         .expect(&format!("Can't send data from read thread: {}!", &path));
+                    converted += 1;
                 }
-            } else {
-                sender
-                    .send(None)
-                    .await
-                    .expect(&format!("Can't send data to write thread"));
-                if self.debug {
-                    println!("extract file {} is over!", &path);
-                }
-                break;
             }
-
-            buffer.clear();
         }
-
+        if !pending.is_empty() {
+            self.add_audit(
+                Level::Warning,
+                format!("unterminated quoted field from line {}, ignored", start),
+            );
+        }
         Ok(())
     }
 }
 
+/// Extract the statements of `broker` with the shared [`Context`].
+///
+/// Return the files failed, with the error of each.
 pub async fn extract_from_file(
     ctx: Arc<Mutex<Context>>,
+    broker: Arc<dyn BrokerParser>,
     paths: Vec<String>,
     sender: Arc<Sender<Option<DeliveryOrder>>>,
     debug: bool,
-) -> std::io::Result<()> {
+) -> Vec<(String, Error)> {
+    let mut paths = paths;
+    let mut failed = vec![];
+
+    if !ctx.lock().await.get_keep_order() {
+        let mut periods = vec![];
+
+        for path in paths {
+            let period = ctx
+                .lock()
+                .await
+                .set_broker(broker.clone())
+                .scan_period(&path)
+                .await
+                .unwrap_or(None);
+
+            periods.push((period.map(|v| v.0), path));
+        }
+        // process the files in chronological order, the files without date go last
+        periods.sort_by_key(|(first, _)| (first.is_none(), first.clone()));
+        paths = periods.into_iter().map(|(_, path)| path).collect();
+        if debug {
+            eprintln!("process files in order: {:?}", paths);
+        }
+    }
     for path in paths {
-        ctx.lock()
+        let ret = ctx
+            .lock()
             .await
             .borrow_mut()
             .set_debug(debug)
-            .extract_from_file(path, sender.clone())
-            .await?;
+            .set_broker(broker.clone())
+            .extract_from_file(path.clone(), sender.clone())
+            .await;
+
+        // the rest files are processed, the error is recorded in audit log
+        if let Err(e) = ret {
+            failed.push((path, e));
+        }
     }
-    Ok(())
+    failed
 }
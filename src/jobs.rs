@@ -0,0 +1,96 @@
+use async_std::channel::{bounded, unbounded, Receiver, Sender};
+use async_std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+
+use crate::broker;
+use crate::error::Error;
+use crate::htsc::{self, Context};
+use crate::DeliveryOrder;
+
+/// Limit how many types of inputs are extracted at the same time.
+///
+/// The bounded channel works as a semaphore, a slot is taken when a type starts.
+#[derive(Debug, Clone, Default)]
+pub struct Jobs {
+    slots: Option<(Sender<()>, Receiver<()>)>,
+}
+
+impl Jobs {
+    /// `None` or zero means no limit.
+    pub fn new(jobs: Option<usize>) -> Self {
+        Self {
+            slots: jobs.filter(|v| *v > 0).map(bounded),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        if let Some((sender, _)) = &self.slots {
+            sender.send(()).await.expect("Can't acquire job slot");
+        }
+    }
+
+    pub async fn release(&self) {
+        if let Some((_, receiver)) = &self.slots {
+            receiver.recv().await.expect("Can't release job slot");
+        }
+    }
+}
+
+/// Extract the `inputs` of each type in parallel, at most `jobs` types at the same time.
+///
+/// Each type is extracted with a context forked from `ctx`, so the balances of a type
+/// start from the opening positions and don't depend on the scheduling. The orders are
+/// sent in the order of types once the type is over, and the findings are joined into `ctx`.
+/// Return the files failed, with the error of each.
+pub async fn extract(
+    ctx: Arc<Mutex<Context>>,
+    inputs: BTreeMap<String, Vec<String>>,
+    sender: Arc<Sender<Option<DeliveryOrder>>>,
+    jobs: Jobs,
+    debug: bool,
+) -> Vec<(String, Error)> {
+    let mut tasks = vec![];
+    let mut failed = vec![];
+
+    for (type_, paths) in inputs {
+        let broker = match broker::find(&type_) {
+            Some(broker) => broker,
+            None => continue,
+        };
+        let forked = Arc::new(Mutex::new(ctx.lock().await.fork()));
+        let jobs = jobs.clone();
+
+        tasks.push(async_std::task::spawn(async move {
+            // the orders are kept until the types before it are sent
+            let (s, r) = unbounded();
+
+            jobs.acquire().await;
+            let failed =
+                htsc::extract_from_file(forked.clone(), broker, paths, Arc::new(s), debug).await;
+
+            jobs.release().await;
+
+            let mut orders = vec![];
+
+            while let Ok(order) = r.try_recv() {
+                orders.push(order);
+            }
+            (forked, orders, failed)
+        }));
+    }
+    for task in tasks {
+        let (forked, orders, errors) = task.await;
+
+        for order in orders {
+            sender
+                .send(order)
+                .await
+                .expect("Can't send data to write thread");
+        }
+        if let Ok(forked) = Arc::try_unwrap(forked) {
+            ctx.lock().await.join(forked.into_inner());
+        }
+        failed.extend(errors);
+    }
+    failed
+}
@@ -0,0 +1,200 @@
+use rust_decimal::Decimal;
+
+use crate::date;
+use crate::round::Rounding;
+use crate::DeliveryOrder;
+
+/// How the rows are distributed into sheets, beside the combined sheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Flat,
+    Code,
+    Month,
+    Account,
+}
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        GroupBy::Flat
+    }
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "flat" => Some(GroupBy::Flat),
+            "code" => Some(GroupBy::Code),
+            "month" => Some(GroupBy::Month),
+            "account" => Some(GroupBy::Account),
+            _ => None,
+        }
+    }
+
+    /// Name of the sheet the order belong to, `None` if the layout is flat.
+    pub fn sheet_of(&self, order: &DeliveryOrder) -> Option<String> {
+        let name = match self {
+            GroupBy::Flat => return None,
+            GroupBy::Code => order.get_code().clone(),
+            GroupBy::Month => date::parse_date(order.get_date())
+                .map(|v| v.format("%Y-%m").to_string())
+                .unwrap_or_default(),
+            GroupBy::Account => order.get_account().clone(),
+        };
+
+        Some(if name.is_empty() {
+            UNKNOWN.to_owned()
+        } else {
+            name
+        })
+    }
+}
+
+const UNKNOWN: &'static str = "未知";
+
+/// Sheets of the output workbook.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    group_by: GroupBy,
+    position: bool,
+    holding: bool,
+    cash: bool,
+    pivot: bool,
+    // attach the source file and line number to the first cell of each row
+    comments: bool,
+    // add 来源文件 and 行号 columns
+    source: bool,
+    // add 佣金, 印花税 and 过户费 columns
+    fees: bool,
+    // add 资金账号 column
+    account: bool,
+    // display format of the dates, e.g. `%Y年%m月%d日`, it is the number format of dates in excel
+    date_format: Option<String>,
+    constant_memory: bool,
+    // decimal places of the numbers, also the number formats in excel
+    rounding: Rounding,
+    // 资金余额 before the first row, continued from the output appended
+    opening_cash: Decimal,
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn with_position(mut self, position: bool) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_holding(mut self, holding: bool) -> Self {
+        self.holding = holding;
+        self
+    }
+
+    pub fn with_cash(mut self, cash: bool) -> Self {
+        self.cash = cash;
+        self
+    }
+
+    pub fn with_pivot(mut self, pivot: bool) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    pub fn with_source(mut self, source: bool) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_fees(mut self, fees: bool) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    pub fn with_account(mut self, account: bool) -> Self {
+        self.account = account;
+        self
+    }
+
+    pub fn with_date_format(mut self, date_format: Option<String>) -> Self {
+        self.date_format = date_format;
+        self
+    }
+
+    pub fn with_constant_memory(mut self, constant_memory: bool) -> Self {
+        self.constant_memory = constant_memory;
+        self
+    }
+
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    pub fn with_opening_cash(mut self, opening_cash: Decimal) -> Self {
+        self.opening_cash = opening_cash;
+        self
+    }
+
+    pub fn get_group_by(&self) -> GroupBy {
+        self.group_by
+    }
+
+    pub fn get_position(&self) -> bool {
+        self.position
+    }
+
+    pub fn get_holding(&self) -> bool {
+        self.holding
+    }
+
+    pub fn get_cash(&self) -> bool {
+        self.cash
+    }
+
+    pub fn get_pivot(&self) -> bool {
+        self.pivot
+    }
+
+    pub fn get_comments(&self) -> bool {
+        self.comments
+    }
+
+    pub fn get_fees(&self) -> bool {
+        self.fees
+    }
+
+    pub fn get_account(&self) -> bool {
+        self.account
+    }
+
+    pub fn get_source(&self) -> bool {
+        self.source
+    }
+
+    pub fn get_date_format(&self) -> Option<&str> {
+        self.date_format.as_deref()
+    }
+
+    pub fn get_constant_memory(&self) -> bool {
+        self.constant_memory
+    }
+
+    pub fn get_rounding(&self) -> &Rounding {
+        &self.rounding
+    }
+
+    pub fn get_opening_cash(&self) -> Decimal {
+        self.opening_cash
+    }
+}
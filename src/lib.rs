@@ -0,0 +1,447 @@
+//! Convert the delivery orders (交割单) of brokers into the import data of 投资账本.
+//!
+//! The statements are parsed line by line into [`DeliveryOrder`], see
+//! [`parse_file`], and written by [`writer::write_orders`].
+
+pub mod aggregate;
+pub mod archive;
+pub mod audit;
+pub mod book;
+pub mod broker;
+pub mod citic;
+pub mod config;
+pub mod custom;
+pub mod date;
+pub mod eastmoney;
+pub mod error;
+pub mod expand;
+pub mod feather;
+pub mod holding;
+pub mod htsc;
+pub mod jobs;
+pub mod layout;
+pub mod mail;
+pub mod mapping;
+pub mod moomoo;
+pub mod number;
+pub mod overseas;
+pub mod pingan;
+pub mod preview;
+pub mod reader;
+pub mod report;
+pub mod robinhood;
+pub mod round;
+pub mod sheet;
+pub mod summary;
+pub mod template;
+pub mod tokenizer;
+pub mod tonghuashun;
+pub mod transfer;
+pub mod tzzb;
+pub mod validate;
+pub mod writer;
+pub mod zhaoshang;
+pub mod zipped;
+
+use async_std::channel::bounded;
+use async_std::stream::{Stream, StreamExt};
+use async_std::sync::{Arc, Mutex};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub const HTSC_TYPE: &'static str = "HTSC";
+pub const CITIC_TYPE: &'static str = "CITIC";
+pub const ZHAOSHANG_TYPE: &'static str = "ZHAOSHANG";
+pub const TONGHUASHUN_TYPE: &'static str = "TONGHUASHUN";
+pub const PINGAN_TYPE: &'static str = "PINGAN";
+// statement of the broker described in config
+pub const CUSTOM_TYPE: &'static str = "CUSTOM";
+// transaction history of 东方财富 APP, in xlsx
+pub const EASTMONEY_TYPE: &'static str = "EASTMONEY";
+// workbook generated by this tool
+pub const TZZB_TYPE: &'static str = "TZZB";
+// trade history CSV of overseas brokers
+pub const MOOMOO_TYPE: &'static str = "MOOMOO";
+pub const ROBINHOOD_TYPE: &'static str = "ROBINHOOD";
+pub const SOURCE_TITLE: [&'static str; 2] = ["来源文件", "行号"];
+pub const FEE_TITLE: [&'static str; 3] = ["佣金", "印花税", "过户费"];
+pub const ACCOUNT_TITLE: &'static str = "资金账号";
+pub const BALANCE_TITLE: &'static str = "资金余额";
+
+/// Parse the delivery order statement `path` into a stream of orders.
+///
+/// The broker is detected from the header, see [`broker::detect`]. The malformed
+/// lines and other findings are dropped, use [`htsc::Context`] directly for them.
+pub fn parse_file(path: &str) -> impl Stream<Item = DeliveryOrder> {
+    let (sender, receiver) = bounded(128);
+    let ctx = Arc::new(Mutex::new(htsc::Context::new()));
+    let broker = broker::detect(path).unwrap_or_else(|| Arc::new(htsc::Htsc));
+
+    async_std::task::spawn(htsc::extract_from_file(
+        ctx,
+        broker,
+        vec![path.to_owned()],
+        Arc::new(sender),
+        false,
+    ));
+    // the end of file is None, and the stream ends when the sender dropped
+    receiver.filter_map(|v| v)
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum Trade {
+    Buy,
+    Sell,
+    In,
+    Out,
+    /// cash dividend, such as 股息入账
+    Dividend,
+    /// interest of cash balance, such as 利息归本
+    Interest,
+    /// fees charged beside the trades, such as 佣金扣收
+    Fee,
+    /// shares allotted without payment, such as 红股入账
+    BonusShares,
+    Ignore,
+    /// user defined category
+    Custom(String),
+}
+
+impl From<String> for Trade {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Buy" => Trade::Buy,
+            "Sell" => Trade::Sell,
+            "In" => Trade::In,
+            "Out" => Trade::Out,
+            "Dividend" => Trade::Dividend,
+            "Interest" => Trade::Interest,
+            "Fee" => Trade::Fee,
+            "BonusShares" => Trade::BonusShares,
+            "Ignore" => Trade::Ignore,
+            _ => Trade::Custom(name),
+        }
+    }
+}
+
+impl From<Trade> for String {
+    fn from(trade: Trade) -> Self {
+        match trade {
+            Trade::Buy => "Buy".to_owned(),
+            Trade::Sell => "Sell".to_owned(),
+            Trade::In => "In".to_owned(),
+            Trade::Out => "Out".to_owned(),
+            Trade::Dividend => "Dividend".to_owned(),
+            Trade::Interest => "Interest".to_owned(),
+            Trade::Fee => "Fee".to_owned(),
+            Trade::BonusShares => "BonusShares".to_owned(),
+            Trade::Ignore => "Ignore".to_owned(),
+            Trade::Custom(name) => name,
+        }
+    }
+}
+
+impl Default for Trade {
+    fn default() -> Self {
+        Trade::Ignore
+    }
+}
+
+impl Trade {
+    /// The 交易类别 in output.
+    pub fn label(&self) -> &str {
+        match self {
+            Trade::Buy => "买入",
+            Trade::Sell => "卖出",
+            Trade::In => "银证转入",
+            Trade::Out => "银证转出",
+            Trade::Dividend => "股息入账",
+            Trade::Interest => "利息归本",
+            Trade::Fee => "费用扣收",
+            Trade::BonusShares => "红股入账",
+            Trade::Ignore => "",
+            Trade::Custom(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DeliveryOrder {
+    code: String,
+    name: String,
+    date: String,
+    kind: String,
+    count: Decimal,
+    // the price and amount may be missing, e.g. 发生金额 derived later
+    #[serde(rename = "price")]
+    prize: Option<Decimal>,
+    amount: Option<Decimal>,
+    // total of the fee columns, 佣金, 印花税 etc.
+    fee: Option<Decimal>,
+    commission: Option<Decimal>,
+    stamp_tax: Option<Decimal>,
+    transfer_fee: Option<Decimal>,
+    // 证券余额 after the order
+    owned: Option<Decimal>,
+    account: String,
+    // the account on the other side of 担保品划转
+    counterpart: String,
+    trade: Trade,
+    // a leg of 融资融券, the cash or shares are borrowed
+    margin: bool,
+    // 成交编号, identify the fill across overlapping statements
+    trade_no: String,
+    // file and line number the row comes from
+    source: String,
+    line: usize,
+}
+
+impl DeliveryOrder {
+    pub fn set_code(&mut self, code: String) {
+        self.code = code;
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn set_date(&mut self, date: String) {
+        self.date = date;
+    }
+
+    pub fn set_kind(&mut self, kind: String) {
+        self.kind = kind;
+    }
+
+    pub fn set_count(&mut self, count: Decimal) {
+        self.count = count;
+    }
+
+    pub fn set_prize(&mut self, prize: Option<Decimal>) {
+        self.prize = prize;
+    }
+
+    pub fn set_amount(&mut self, amount: Option<Decimal>) {
+        self.amount = amount;
+    }
+
+    pub fn set_fee(&mut self, fee: Option<Decimal>) {
+        self.fee = fee;
+    }
+
+    pub fn set_commission(&mut self, commission: Option<Decimal>) {
+        self.commission = commission;
+    }
+
+    pub fn set_stamp_tax(&mut self, stamp_tax: Option<Decimal>) {
+        self.stamp_tax = stamp_tax;
+    }
+
+    pub fn set_transfer_fee(&mut self, transfer_fee: Option<Decimal>) {
+        self.transfer_fee = transfer_fee;
+    }
+
+    pub fn set_trade_no(&mut self, trade_no: String) {
+        self.trade_no = trade_no;
+    }
+
+    pub fn set_margin(&mut self, margin: bool) {
+        self.margin = margin;
+    }
+
+    pub fn set_owned(&mut self, owned: Option<Decimal>) {
+        self.owned = owned;
+    }
+
+    pub fn set_account(&mut self, account: String) {
+        self.account = account;
+    }
+
+    pub fn set_counterpart(&mut self, counterpart: String) {
+        self.counterpart = counterpart;
+    }
+
+    pub fn set_source(&mut self, source: String) {
+        self.source = source;
+    }
+
+    pub fn set_line(&mut self, line: usize) {
+        self.line = line;
+    }
+
+    pub fn with_code(mut self, code: String) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn with_date(mut self, date: String) -> Self {
+        self.date = date;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: String) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_count(mut self, count: Decimal) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn with_prize(mut self, prize: Option<Decimal>) -> Self {
+        self.prize = prize;
+        self
+    }
+
+    pub fn with_amount(mut self, amount: Option<Decimal>) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn with_fee(mut self, fee: Option<Decimal>) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn with_commission(mut self, commission: Option<Decimal>) -> Self {
+        self.commission = commission;
+        self
+    }
+
+    pub fn with_stamp_tax(mut self, stamp_tax: Option<Decimal>) -> Self {
+        self.stamp_tax = stamp_tax;
+        self
+    }
+
+    pub fn with_transfer_fee(mut self, transfer_fee: Option<Decimal>) -> Self {
+        self.transfer_fee = transfer_fee;
+        self
+    }
+
+    pub fn with_trade_no(mut self, trade_no: String) -> Self {
+        self.trade_no = trade_no;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: bool) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_owned(mut self, owned: Option<Decimal>) -> Self {
+        self.owned = owned;
+        self
+    }
+
+    pub fn with_account(mut self, account: String) -> Self {
+        self.account = account;
+        self
+    }
+
+    pub fn with_trade(mut self, trade: Trade) -> Self {
+        self.trade = trade;
+        self
+    }
+
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_date(&self) -> &String {
+        &self.date
+    }
+
+    pub fn get_kind(&self) -> &String {
+        &self.kind
+    }
+
+    pub fn get_count(&self) -> Decimal {
+        self.count
+    }
+
+    pub fn get_prize(&self) -> Option<Decimal> {
+        self.prize
+    }
+
+    pub fn get_amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+
+    pub fn get_fee(&self) -> Option<Decimal> {
+        self.fee
+    }
+
+    /// 佣金, or 手续费 of the brokers which have no 佣金 column.
+    pub fn get_commission(&self) -> Option<Decimal> {
+        self.commission
+    }
+
+    pub fn get_stamp_tax(&self) -> Option<Decimal> {
+        self.stamp_tax
+    }
+
+    pub fn get_transfer_fee(&self) -> Option<Decimal> {
+        self.transfer_fee
+    }
+
+    pub fn get_trade_no(&self) -> &String {
+        &self.trade_no
+    }
+
+    /// Return true if the order is a leg of 融资融券.
+    pub fn is_margin(&self) -> bool {
+        self.margin
+    }
+
+    pub fn get_owned(&self) -> Option<Decimal> {
+        self.owned
+    }
+
+    pub fn get_account(&self) -> &String {
+        &self.account
+    }
+
+    pub fn get_counterpart(&self) -> &String {
+        &self.counterpart
+    }
+
+    pub fn get_trade(&self) -> &Trade {
+        &self.trade
+    }
+
+    pub fn get_source(&self) -> &String {
+        &self.source
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self.trade {
+            Trade::Ignore => false,
+            _ => true,
+        }
+    }
+}
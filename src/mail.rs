@@ -0,0 +1,80 @@
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::audit::{Audit, Level};
+use crate::report::{self, Profit};
+use crate::summary::Summary;
+use crate::writer::Format;
+
+fn default_port() -> u16 {
+    465
+}
+
+/// SMTP settings of the summary email, sent after each run if configured.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Smtp {
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    username: String,
+    password: String,
+    from: String,
+    to: Vec<String>,
+}
+
+impl Smtp {
+    /// Send the summary and `profits` of this run, with the generated `output` attached.
+    pub fn send(
+        &self,
+        output: &str,
+        format: Format,
+        summary: &Summary,
+        profits: &[Profit],
+        audit: &Audit,
+    ) -> color_eyre::Result<()> {
+        let mut builder = Message::builder().from(self.from.parse()?).subject(format!(
+            "delivery order: {} rows converted",
+            summary.get_rows()
+        ));
+
+        for to in self.to.iter() {
+            builder = builder.to(to.parse()?);
+        }
+
+        let body = format!(
+            "output: {}\nrows converted: {}\nwarnings: {}\nerrors: {}\n\n{}\n\n{}",
+            output,
+            summary.get_rows(),
+            audit.count(Level::Warning) - audit.count(Level::Error),
+            audit.count(Level::Error),
+            summary.render(),
+            report::render(profits)
+        );
+        let name = Path::new(output)
+            .file_name()
+            .map(|v| v.to_string_lossy().to_string())
+            .unwrap_or_else(|| output.to_owned());
+        let message = builder.multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body))
+                .singlepart(Attachment::new(name).body(
+                    std::fs::read(output)?,
+                    ContentType::parse(format.get_content_type())?,
+                )),
+        )?;
+        let mailer = SmtpTransport::relay(&self.host)?
+            .port(self.port)
+            .credentials(Credentials::new(
+                self.username.clone(),
+                self.password.clone(),
+            ))
+            .build();
+
+        mailer.send(&message)?;
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
-mod htsc;
-
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::atomic::AtomicI32;
 
 use aopt::prelude::*;
+use colored::Colorize;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 
 use async_std::sync::Mutex;
 use async_std::{
@@ -11,9 +12,19 @@ use async_std::{
     sync::Arc,
 };
 
-use xlsxwriter::{Workbook, XlsxError};
+use delivery_order::book::OrderBook;
+use delivery_order::htsc::OnError;
+use delivery_order::jobs::Jobs;
+use delivery_order::layout::{GroupBy, Layout};
+use delivery_order::mapping::TradeMap;
+use delivery_order::summary::Summary;
+use delivery_order::writer::{self, Format};
+use delivery_order::{
+    aggregate, archive, audit, broker, config, custom, date, expand, holding, htsc, jobs, preview,
+    reader, report, template, transfer, tzzb, validate, zipped,
+};
+use delivery_order::{DeliveryOrder, TZZB_TYPE};
 
-const HTSC_TYPE: &'static str = "HTSC";
 const OUTPUT: &'static str = "output.xlsx";
 
 #[async_std::main]
@@ -23,290 +34,957 @@ async fn main() -> color_eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|v| v.as_str()) {
+        Some("extract") => extract(args.split_off(1)).await,
+        Some("merge") => merge(args.split_off(1)),
+        Some("report") => report(args.split_off(1)),
+        Some("validate") => validate(args.split_off(1)),
+        // convert the statements without subcommand, as the earlier versions
+        _ => extract(args).await,
+    }
+}
+
+/// Convert the statements into output, the default subcommand.
+async fn extract(args: Vec<String>) -> color_eyre::Result<()> {
     let (s, r) = bounded(128);
     let sender = Arc::new(s);
     let receiver = Arc::new(r);
     let htsc_context = Arc::new(Mutex::new(htsc::Context::new()));
     let mut parser = ForwardParser::default();
 
+    parser.add_opt("-t=s")?.add_alias("--type")?.commit()?;
+    // default is the output of config, or output.xlsx
+    parser.add_opt("-o=s")?.add_alias("--output")?.commit()?;
+    parser.add_opt("-f=s")?.add_alias("--format")?.commit()?;
+    parser.add_opt("-d=b")?.add_alias("--debug")?.commit()?;
+    add_layout_opts(&mut parser)?;
+    parser.add_opt("--no-summary=b")?.commit()?;
+    parser.add_opt("-a=b")?.add_alias("--append")?.commit()?;
+    parser.add_opt("--aggregate-fills=b")?.commit()?;
+    parser.add_opt("--amount-tolerance=f")?.commit()?;
+    parser.add_opt("--keep-order=b")?.commit()?;
+    parser.add_opt("--strict-balance=b")?.commit()?;
+    parser.add_opt("--strict=b")?.commit()?;
+    parser.add_opt("--dedup=b")?.commit()?;
+    parser.add_opt("--on-error=s")?.commit()?;
     parser
-        .add_opt("-t=s!")?
-        .add_alias("--type")?
-        .set_default_value(HTSC_TYPE.into())
+        .add_opt("--owned-source=s")?
+        .set_default_value("computed".into())
         .commit()?;
     parser
-        .add_opt("-o=s")?
-        .add_alias("--output")?
-        .set_default_value(OUTPUT.into())
+        .add_opt("--balance-scope=s")?
+        .set_default_value("global".into())
         .commit()?;
-    parser.add_opt("-d=b")?.add_alias("--debug")?.commit()?;
+    parser.add_opt("--limit=u")?.commit()?;
+    // extract the types in parallel, the balances no longer run across the types
+    parser.add_opt("-j=u")?.add_alias("--jobs")?.commit()?;
+    parser.add_opt("--from=s")?.commit()?;
+    parser.add_opt("--to=s")?.commit()?;
+    parser.add_opt("--code=a")?.commit()?;
+    parser.add_opt("--name-contains=s")?.commit()?;
+    parser.add_opt("--encoding=s")?.commit()?;
+    parser.add_opt("--zip-pattern=s")?.commit()?;
+    parser.add_opt("-r=b")?.add_alias("--recursive")?.commit()?;
+    parser.add_opt("--preview=u")?.commit()?;
+    parser.add_opt("--config=s")?.commit()?;
+    parser.add_opt("--archive=s")?.commit()?;
+    parser.add_opt("--template=s")?.commit()?;
+    parser.add_opt("--initial-positions=s")?.commit()?;
+    parser.add_opt("-w=s")?.add_alias("--widths")?.commit()?;
+    parser.add_opt("-c=s")?.add_alias("--columns")?.commit()?;
 
     let uid = parser.add_opt("input=p!@*")?.commit()?;
     let counter = Arc::new(AtomicI32::new(0));
     let counter_reader = counter.clone();
 
-    type Input = HashMap<String, Vec<String>>;
+    type Input = BTreeMap<String, Vec<String>>;
 
     parser.add_callback(
         uid,
         simple_pos_mut_cb!(move |uid, set: &mut SimpleSet, path, _, _| {
-            let file_type = set["--type"].get_value().as_str().unwrap().clone();
             let opt = set[uid].as_mut();
-            let mut inputs: Input;
+            let mut paths: Vec<String>;
 
-            if let Some(inner_data) = opt.get_value_mut().downcast_mut::<Input>() {
-                inputs = std::mem::take(inner_data);
+            if let Some(inner_data) = opt.get_value_mut().downcast_mut::<Vec<String>>() {
+                paths = std::mem::take(inner_data);
             } else {
-                inputs = Input::default();
+                paths = vec![];
             }
-            match file_type.as_str() {
-                HTSC_TYPE => {
-                    inputs
-                        .entry(String::from(file_type))
-                        .or_insert(vec![])
-                        .push(path.to_owned());
-                }
-                _ => {
-                    panic!("Unknow file type: {}", file_type);
-                }
-            }
-            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            Ok(Some(OptValue::from_any(Box::new(inputs))))
+            // expanded after all the options are parsed
+            paths.push(path.to_owned());
+            Ok(Some(OptValue::from_any(Box::new(paths))))
         }),
     );
 
-    getopt!(&mut std::env::args().skip(1), parser)?;
+    getopt!(&mut args.into_iter(), parser)?;
 
-    let inputs;
+    let mut inputs = Input::default();
     let debug = *parser["--debug"].get_value().as_bool().unwrap_or(&false);
-
-    if let Some(inner_data) = parser["input"].get_value_mut().downcast_mut::<Input>() {
-        inputs = std::mem::take(inner_data);
-    } else {
-        inputs = Input::default();
-    }
-    if debug {
-        println!("got file map: {:?}", inputs);
-        println!("got output file count = {:?}", counter_reader);
-    }
-    if counter_reader.load(std::sync::atomic::Ordering::SeqCst) > 0 {
-        for (type_, paths) in inputs.iter() {
-            match type_.as_str() {
-                HTSC_TYPE => {
-                    async_std::task::spawn(htsc::extract_from_file(
-                        htsc_context.clone(),
-                        paths.clone(),
-                        sender.clone(),
-                        debug,
-                    ));
-                }
-                _ => {}
+    let layout = get_layout(&parser)?;
+    let no_summary = *parser["--no-summary"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false);
+    let strict_balance = *parser["--strict-balance"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false);
+    let strict = *parser["--strict"].get_value().as_bool().unwrap_or(&false);
+    let append = *parser["--append"].get_value().as_bool().unwrap_or(&false);
+    let aggregate = *parser["--aggregate-fills"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false);
+    // the options of command line override the defaults in config
+    let config = match parser["--config"].get_value().as_str() {
+        Some(path) => config::Config::load(path)?,
+        None => config::Config::default(),
+    };
+    // the custom broker must be registered before detecting the inputs
+    if !config.custom.is_empty() {
+        let encoding = match &config.custom.encoding {
+            Some(label) => Some(
+                encoding_rs::Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| color_eyre::eyre::eyre!("Unknow encoding: {}", label))?,
+            ),
+            None => None,
+        };
+
+        custom::register(custom::Custom::new(&config.custom, encoding));
+    }
+    let file_type = parser["--type"]
+        .get_value()
+        .as_str()
+        .cloned()
+        .or(config.type_);
+    let recursive = *parser["--recursive"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false);
+    let pattern = parser["--zip-pattern"].get_value().as_str().cloned();
+    let output_name = parser["--output"]
+        .get_value()
+        .as_str()
+        .cloned()
+        .or(config.output)
+        .unwrap_or_else(|| OUTPUT.to_owned());
+    let mut paths = vec![];
+
+    for arg in parser["input"]
+        .get_value_mut()
+        .downcast_mut::<Vec<String>>()
+        .map(std::mem::take)
+        .unwrap_or_default()
+    {
+        let expanded = expand::expand(&arg, recursive, Some(&output_name))
+            .map_err(|e| color_eyre::eyre::eyre!("Can not expand input {}: {:?}", arg, e))?;
+
+        if expanded.is_empty() {
+            return Err(color_eyre::eyre::eyre!("No input file matched: {}", arg));
+        }
+        for path in expanded {
+            // the statements in zip file are processed as separate inputs
+            if zipped::is_zip_path(&path) {
+                paths.extend(zipped::entries(&path, pattern.as_deref()).map_err(|e| {
+                    color_eyre::eyre::eyre!("Can not read zip file {}: {:?}", path, e)
+                })?);
+            } else {
+                paths.push(path);
             }
         }
     }
 
-    let output_name = parser.get_value("--output")?.unwrap().as_str().unwrap();
+    for path in paths.iter() {
+        let file_type = input_type(file_type.as_ref(), path)?;
 
-    if counter_reader.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+        match file_type.as_str() {
+            // all the inputs are parsed by the brokers of registry
+            name if broker::find(name).is_some() => {
+                inputs
+                    .entry(String::from(file_type))
+                    .or_insert(vec![])
+                    .push(path.to_owned());
+            }
+            _ => {
+                return Err(color_eyre::eyre::eyre!("Unknow file type: {}", file_type));
+            }
+        }
+    }
+    counter.fetch_add(paths.len() as i32, std::sync::atomic::Ordering::SeqCst);
+    if let Some(widths) = parser["--widths"].get_value().as_str() {
+        let widths = widths
+            .split(',')
+            .map(|v| v.trim().parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()?;
+
+        htsc_context.lock().await.set_widths(Some(widths));
+    }
+    let rounding = config.round;
+    let layout = layout.with_rounding(rounding.clone());
+    let accounts = config.account;
+    let smtp = config.smtp;
+    let beancount = config.beancount;
+
+    {
+        let mut ctx = htsc_context.lock().await;
+
+        // the mapping of config wins over the custom broker
+        ctx.get_trade_map_mut().extend(config.custom.trade);
+        ctx.get_trade_map_mut().extend(config.trade);
+        ctx.get_trade_map_mut().extend_aliases(config.business);
+        for name in config.ignore.add {
+            ctx.get_trade_map_mut().add_ignore(name);
+        }
+        for name in config.ignore.remove {
+            ctx.get_trade_map_mut().remove_ignore(name);
+        }
+        ctx.get_trade_map_mut().extend_categories(config.category);
+        ctx.get_trade_map_mut().add_rules(config.rule)?;
+        ctx.set_units(config.unit);
+        ctx.set_aliases(config.alias);
+        ctx.set_signs(config.sign);
+    }
+    if *parser["--dedup"].get_value().as_bool().unwrap_or(&false) {
+        htsc_context.lock().await.set_dedup(true);
+    }
+    if *parser["--keep-order"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false)
+    {
+        htsc_context.lock().await.set_keep_order(true);
+    }
+    let preview = parser["--preview"]
+        .get_value()
+        .as_uint()
+        .map(|v| *v as usize);
+    let limit = parser["--limit"].get_value().as_uint().map(|v| *v as usize);
+    let jobs = parser["--jobs"].get_value().as_uint().map(|v| *v as usize);
+    let mut range = [None, None];
+
+    for (idx, name) in ["--from", "--to"].iter().enumerate() {
+        if let Some(value) = parser[*name].get_value().as_str() {
+            range[idx] =
+                Some(date::parse_date(value).ok_or_else(|| {
+                    color_eyre::eyre::eyre!("Unknow date of {}: {}", name, value)
+                })?);
+        }
+    }
+    let [from, to] = range;
+    let codes = parser["--code"]
+        .get_value()
+        .as_vec()
+        .cloned()
+        .unwrap_or_default();
+    let name_contains = parser["--name-contains"].get_value().as_str().cloned();
+
+    htsc_context.lock().await.set_limit(limit);
+    let owned_source = parser["--owned-source"]
+        .get_value()
+        .as_str()
+        .unwrap()
+        .clone();
+
+    htsc_context.lock().await.set_owned_source(
+        htsc::OwnedSource::parse(&owned_source)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Unknow owned source: {}", owned_source))?,
+    );
+    let balance_scope = parser["--balance-scope"]
+        .get_value()
+        .as_str()
+        .unwrap()
+        .clone();
+
+    htsc_context.lock().await.set_balance_scope(
+        htsc::BalanceScope::parse(&balance_scope)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Unknow balance scope: {}", balance_scope))?,
+    );
+    if let Some(label) = parser["--encoding"].get_value().as_str() {
+        let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| color_eyre::eyre::eyre!("Unknow encoding: {}", label))?;
+
+        htsc_context.lock().await.set_encoding(Some(encoding));
+    }
+    let on_error = match parser["--on-error"].get_value().as_str() {
+        Some(value) => Some(
+            OnError::parse(value)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Unknow on error: {}", value))?,
+        ),
+        None => None,
+    };
+
+    htsc_context.lock().await.set_on_error(on_error);
+    if let Some(tolerance) = parser["--amount-tolerance"].get_value().as_flt() {
+        htsc_context.lock().await.set_tolerance(*tolerance);
+    }
+    if let Some(columns) = parser["--columns"].get_value().as_str() {
+        let columns = columns.split(',').map(|v| v.trim().to_owned()).collect();
+
+        htsc_context.lock().await.set_columns(Some(columns));
+    }
+
+    let format = match parser["--format"].get_value().as_str() {
+        Some(name) => Format::parse(name)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Unknow output format: {}", name))?,
+        None => Format::from_path(&output_name),
+    };
+    let template = match parser["--template"].get_value().as_str() {
+        // the order rows of template are not copied, only the formatting
+        Some(path) => Some(template::read(path)?),
+        None => None,
+    };
+    let mut previous = vec![];
+    let mut opening_cash = Decimal::ZERO;
+    // only workbooks generated by this tool can be appended
+    let base = if append && format.is_workbook() && std::path::Path::new(&output_name).exists() {
+        Some(output_name.clone())
+    } else {
+        None
+    };
+
+    if let Some(base) = &base {
+        previous = tzzb::read_orders(base)?;
+
+        // continue the balances from the last row of each code
+        let mut owned = HashMap::new();
+        let mut ctx = htsc_context.lock().await;
+
+        for order in previous.iter().filter(|v| !v.get_code().is_empty()) {
+            // the accounts in output may be renamed by config
+            let account = accounts
+                .iter()
+                .find(|(_, name)| *name == order.get_account())
+                .map(|(account, _)| account.clone())
+                .unwrap_or_else(|| order.get_account().clone());
+
+            owned.insert(
+                (account, order.get_code().clone()),
+                order
+                    .get_owned()
+                    .and_then(|v| v.trunc().to_i64())
+                    .unwrap_or(0),
+            );
+        }
+        for ((account, code), count) in owned {
+            ctx.add_count(account, code, count);
+        }
+        // the previous rows are written again, so the cash starts before them
+        if let Some(balance) = tzzb::read_cash_balance(base)? {
+            let trades = ctx.get_trade_map();
+            let paid: Decimal = previous
+                .iter()
+                .filter(|v| trades.category(v.get_trade()).is_cash())
+                .map(|v| v.get_amount().unwrap_or_default())
+                .sum();
+
+            opening_cash = balance - paid;
+        }
         if debug {
-            println!("got output file name = {:?}", output_name);
+            eprintln!("got {} rows from previous output", previous.len());
         }
-        write_htsc_to_tzzb_excel(output_name.to_owned(), receiver.clone(), counter_reader).await?;
     }
-    Ok(())
-}
+    let layout = layout.with_opening_cash(opening_cash);
+    if let Some(path) = parser["--initial-positions"].get_value().as_str() {
+        let positions = holding::read_positions(path)?;
+        let mut ctx = htsc_context.lock().await;
 
-async fn write_htsc_to_tzzb_excel(
-    path: String,
-    rec: Arc<Receiver<Option<DeliveryOrder>>>,
-    counter_reader: Arc<AtomicI32>,
-) -> Result<(), XlsxError> {
-    let title = htsc::Context::gen_title();
-    let workbook = Workbook::new(&path);
-    let mut sheet = workbook.add_worksheet(None)?;
-    let mut counter = 0;
-    let mut read_stop_counter = 0;
-
-    for idx in 0..title.len() {
-        sheet.write_string(counter, idx as u16, &title[idx], None)?;
+        if debug {
+            eprintln!("got {} initial positions from {}", positions.len(), path);
+        }
+        for (account, code, count) in positions {
+            ctx.add_count(account, code, count);
+        }
     }
+    if debug {
+        eprintln!("got file map: {:?}", inputs);
+        eprintln!("got output file count = {:?}", counter_reader);
+    }
+    let extraction = if counter_reader.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+        let inputs = inputs.clone();
+        let sender = sender.clone();
+        let htsc_context = htsc_context.clone();
+
+        // without `--jobs` the types are extracted one by one in the order of name,
+        // the balances shared by the types don't depend on the scheduling
+        Some(async_std::task::spawn(async move {
+            if jobs.is_some() {
+                return jobs::extract(htsc_context, inputs, sender, Jobs::new(jobs), debug).await;
+            }
+            let mut failed = vec![];
+
+            for (type_, paths) in inputs.into_iter() {
+                if let Some(broker) = broker::find(&type_) {
+                    failed.extend(
+                        htsc::extract_from_file(
+                            htsc_context.clone(),
+                            broker,
+                            paths,
+                            sender.clone(),
+                            debug,
+                        )
+                        .await,
+                    );
+                }
+            }
+            failed
+        }))
+    } else {
+        None
+    };
 
-    loop {
-        if let Some(order) = rec
-            .recv()
-            .await
-            .expect("Unable to receive from read thread")
-        {
-            counter += 1;
-            sheet.write_string(counter, 0, order.get_date(), None)?;
-            sheet.write_string(counter, 1, order.get_code(), None)?;
-            sheet.write_string(counter, 2, order.get_name(), None)?;
-            sheet.write_string(counter, 3, order.get_kind(), None)?;
-            sheet.write_string(counter, 4, order.get_count(), None)?;
-            sheet.write_string(counter, 5, order.get_prize(), None)?;
-            sheet.write_string(counter, 6, order.get_amount(), None)?;
-            sheet.write_string(counter, 7, order.get_owned(), None)?;
-        } else {
-            read_stop_counter += 1;
-            if read_stop_counter == counter_reader.load(std::sync::atomic::Ordering::SeqCst) {
-                break;
+    if counter_reader.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+        // rows from previous output go first
+        let mut orders = previous;
+
+        // the balances are computed from all the rows, only the output is filtered
+        orders.extend(
+            receive_orders(receiver.clone(), counter_reader)
+                .await
+                .into_iter()
+                .filter(|v| date::in_range(v.get_date(), from, to))
+                .filter(|v| codes.is_empty() || codes.contains(v.get_code()))
+                .filter(|v| {
+                    name_contains
+                        .as_ref()
+                        .map(|name| v.get_name().contains(name.as_str()))
+                        .unwrap_or(true)
+                }),
+        );
+        // the output is still written with the other files, the run fails at the end
+        let failed = match extraction {
+            Some(extraction) => extraction.await,
+            None => vec![],
+        };
+
+        for (path, e) in failed.iter() {
+            eprintln!("{} {}: {}", "can not extract".bold().red(), path, e);
+        }
+        if !htsc_context.lock().await.get_keep_order() {
+            // stable, the rows of same day keep the order in statements
+            orders.sort_by_key(|v| {
+                let date = date::parse_date(v.get_date());
+
+                (date.is_none(), date)
+            });
+        }
+        for order in orders.iter_mut() {
+            if let Some(alias) = accounts.get(order.get_account()) {
+                order.set_account(alias.clone());
             }
         }
-    }
+        transfer::pair_transfers(&mut orders);
+        if aggregate {
+            orders = aggregate::aggregate_fills(orders);
+        }
+        if !rounding.is_empty() {
+            orders.iter_mut().for_each(|v| rounding.apply(v));
+        }
+        let book = OrderBook::new(orders);
 
-    println!("--> read count = {}, {:?}", counter, counter_reader);
+        if debug {
+            eprintln!("got {} orders", book.len());
+            eprintln!("got output file name = {:?}", output_name);
+        }
+        let (trades, audit, diverged, malformed, unknown, duplicates) = {
+            let mut ctx = htsc_context.lock().await;
+
+            ctx.check_gaps();
+            ctx.check_overlaps();
+            (
+                ctx.get_trade_map().clone(),
+                ctx.get_audit().clone(),
+                ctx.get_diverged().len(),
+                ctx.get_malformed().clone(),
+                ctx.get_unknown().clone(),
+                ctx.get_duplicates(),
+            )
+        };
+
+        // nothing is written, add the names to `[trade]` or `[business]` of config
+        if strict && !unknown.is_empty() {
+            return Err(color_eyre::eyre::eyre!(
+                "unknown 业务名称: {}",
+                unknown.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        let malformed = match on_error {
+            Some(OnError::Abort) if !malformed.is_empty() => {
+                let first = &malformed[0];
+
+                return Err(color_eyre::eyre::eyre!(
+                    "{}:{}: {}",
+                    first.get_path(),
+                    first.get_line(),
+                    first.get_error()
+                ));
+            }
+            Some(OnError::Skip) if !malformed.is_empty() => {
+                eprintln!("skipped {} malformed lines", malformed.len());
+                vec![]
+            }
+            _ => malformed,
+        };
+
+        for record in malformed.iter() {
+            eprintln!(
+                "{}:{}: {} => {}",
+                record.get_path(),
+                record.get_line(),
+                record.get_error(),
+                record.get_content()
+            );
+        }
+        if let Some(rows) = preview {
+            preview::print(book.get_orders(), rows);
+            if !preview::confirm(&format!("write {} rows to {}?", book.len(), output_name))? {
+                eprintln!("nothing written");
+                return Ok(());
+            }
+        }
+        let mut summary = Summary::new();
 
-    workbook.close()?;
+        summary.set_duplicates(duplicates);
 
-    Ok(())
-}
+        for order in book.get_orders().iter() {
+            summary.add(order);
+        }
+        match format {
+            // the extra sheets and accounts come from the options and config
+            Format::Xlsx => {
+                writer::xlsx::write_orders(
+                    &output_name,
+                    book.get_orders(),
+                    template.as_ref(),
+                    &trades,
+                    &audit,
+                    &malformed,
+                    &layout,
+                )?;
+            }
+            Format::Beancount => {
+                writer::beancount::write_orders(&output_name, book.get_orders(), &beancount)?;
+            }
+            format => {
+                writer::write_orders(&output_name, format, book.get_orders(), &layout)?;
+            }
+        }
+        if let Some(dir) = parser["--archive"].get_value().as_str() {
+            // archive the zip file instead of its entries, the standard input is skipped
+            let failed_paths: BTreeSet<&str> = failed
+                .iter()
+                .map(|(path, _)| zipped::real_path(path))
+                .collect();
+            // the failed files are kept to be extracted again
+            let paths: BTreeSet<&str> = inputs
+                .values()
+                .flatten()
+                .map(|v| zipped::real_path(v))
+                .filter(|v| *v != reader::STDIN_PATH && !failed_paths.contains(v))
+                .collect();
+
+            for path in paths {
+                let dest = archive::archive(dir, path)?;
+
+                if debug {
+                    eprintln!("archive {} to {}", path, dest.display());
+                }
+            }
+        }
+        // keep the standard output clean for the next stage of pipe
+        let piped = output_name == writer::json::STDOUT_PATH;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub enum Trade {
-    Buy,
-    Sell,
-    In,
-    Out,
-    Ignore,
-}
+        if !no_summary && !piped {
+            summary.print();
+        }
+        // the audit log goes to standard error, it is shown even if piped
+        if !audit.is_empty() {
+            eprintln!("audit log:");
+            audit.print(if debug {
+                audit::Level::Info
+            } else {
+                audit::Level::Warning
+            });
+        }
+        if let Some(smtp) = smtp.as_ref().filter(|_| !piped) {
+            let profits = report::profits(book.get_orders());
 
-impl Default for Trade {
-    fn default() -> Self {
-        Trade::Ignore
+            smtp.send(&output_name, format, &summary, &profits, &audit)?;
+        }
+        if !failed.is_empty() {
+            return Err(color_eyre::eyre::eyre!(
+                "can not extract {} input files",
+                failed.len()
+            ));
+        }
+        // the report is written anyway, so the mismatches can be inspected
+        if strict_balance && diverged > 0 {
+            return Err(color_eyre::eyre::eyre!(
+                "证券余额 mismatch of {} codes",
+                diverged
+            ));
+        }
     }
+    Ok(())
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct DeliveryOrder {
-    code: String,
-    name: String,
-    date: String,
-    kind: String,
-    count: String,
-    prize: String,
-    amount: String,
-    owned: String,
-    trade: Trade,
-}
+/// Combine the workbooks generated by this tool into one output.
+///
+/// The rows are sorted by date, the rows of same day keep the order of inputs.
+fn merge(args: Vec<String>) -> color_eyre::Result<()> {
+    let mut parser = ForwardParser::default();
 
-impl DeliveryOrder {
-    pub fn set_code(&mut self, code: String) {
-        self.code = code;
-    }
+    parser
+        .add_opt("-o=s")?
+        .add_alias("--output")?
+        .set_default_value(OUTPUT.into())
+        .commit()?;
+    parser.add_opt("-f=s")?.add_alias("--format")?.commit()?;
+    parser.add_opt("-d=b")?.add_alias("--debug")?.commit()?;
+    add_layout_opts(&mut parser)?;
+    add_workbook_input(&mut parser)?;
 
-    pub fn set_name(&mut self, name: String) {
-        self.name = name;
-    }
+    getopt!(&mut args.into_iter(), parser)?;
 
-    pub fn set_date(&mut self, date: String) {
-        self.date = date;
+    let debug = *parser["--debug"].get_value().as_bool().unwrap_or(&false);
+    let layout = get_layout(&parser)?;
+    let output_name = parser["--output"].get_value().as_str().unwrap().clone();
+    let format = match parser["--format"].get_value().as_str() {
+        Some(name) => Format::parse(name)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Unknow output format: {}", name))?,
+        None => Format::from_path(&output_name),
+    };
+    let mut orders = read_workbooks(&take_workbooks(&mut parser)?, debug)?;
+
+    orders.sort_by_key(|v| date::parse_date(v.get_date()));
+    writer::write_orders(&output_name, format, &orders, &layout)?;
+    if debug {
+        eprintln!("write {} rows to {}", orders.len(), output_name);
     }
+    Ok(())
+}
 
-    pub fn set_kind(&mut self, kind: String) {
-        self.kind = kind;
-    }
+/// Print the summary and profit of workbooks generated by this tool.
+fn report(args: Vec<String>) -> color_eyre::Result<()> {
+    let mut parser = ForwardParser::default();
 
-    pub fn set_count(&mut self, count: String) {
-        self.count = count;
-    }
+    parser.add_opt("-d=b")?.add_alias("--debug")?.commit()?;
+    parser.add_opt("--no-summary=b")?.commit()?;
+    // the exchange rates of `--foreign-income` are read from the [rate] of config
+    parser.add_opt("--config=s")?.commit()?;
+    // print the yearly 境外所得 of the overseas codes
+    parser.add_opt("--foreign-income=b")?.commit()?;
+    // write the closed lots of the overseas codes as CSV in the layout of Form 8949
+    parser.add_opt("--form-8949=s")?.commit()?;
+    add_workbook_input(&mut parser)?;
 
-    pub fn set_prize(&mut self, prize: String) {
-        self.prize = prize;
-    }
+    getopt!(&mut args.into_iter(), parser)?;
 
-    pub fn set_amount(&mut self, amount: String) {
-        self.amount = amount;
-    }
+    let debug = *parser["--debug"].get_value().as_bool().unwrap_or(&false);
+    let no_summary = *parser["--no-summary"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false);
+    let orders = read_workbooks(&take_workbooks(&mut parser)?, debug)?;
 
-    pub fn set_owned(&mut self, owned: String) {
-        self.owned = owned;
-    }
+    if !no_summary {
+        let mut summary = Summary::new();
 
-    pub fn with_code(mut self, code: String) -> Self {
-        self.code = code;
-        self
+        for order in orders.iter() {
+            summary.add(order);
+        }
+        summary.print();
+    }
+    println!("{}", report::render(&report::profits(&orders)));
+    if *parser["--foreign-income"]
+        .get_value()
+        .as_bool()
+        .unwrap_or(&false)
+    {
+        let rates = match parser["--config"].get_value().as_str() {
+            Some(path) => config::Config::load(path)?.rate,
+            None => HashMap::new(),
+        };
+        let incomes = report::foreign_incomes(&orders, &rates).map_err(|(currency, year)| {
+            color_eyre::eyre::eyre!(
+                "Missing the exchange rate of {} in {}, please set it in the [rate] of --config",
+                currency,
+                year
+            )
+        })?;
+
+        println!("{}", report::render_foreign(&incomes));
+    }
+    if let Some(path) = parser["--form-8949"].get_value().as_str() {
+        let lots = report::closed_lots(&orders);
+
+        writer::csv::write_lots(path, &lots)?;
+        if debug {
+            eprintln!("write {} lots to {}", lots.len(), path);
+        }
     }
+    Ok(())
+}
 
-    pub fn with_name(mut self, name: String) -> Self {
-        self.name = name;
-        self
-    }
+/// Check the 证券余额 of each workbook generated by this tool, fail if any mismatch.
+fn validate(args: Vec<String>) -> color_eyre::Result<()> {
+    let mut parser = ForwardParser::default();
 
-    pub fn with_date(mut self, date: String) -> Self {
-        self.date = date;
-        self
-    }
+    parser.add_opt("-d=b")?.add_alias("--debug")?.commit()?;
+    parser.add_opt("--config=s")?.commit()?;
+    add_workbook_input(&mut parser)?;
 
-    pub fn with_kind(mut self, kind: String) -> Self {
-        self.kind = kind;
-        self
-    }
+    getopt!(&mut args.into_iter(), parser)?;
 
-    pub fn with_count(mut self, count: String) -> Self {
-        self.count = count;
-        self
+    let debug = *parser["--debug"].get_value().as_bool().unwrap_or(&false);
+    let mut trades = TradeMap::new();
+    let mut mismatched = 0;
+
+    // the user defined categories change the holdings
+    if let Some(path) = parser["--config"].get_value().as_str() {
+        let config = config::Config::load(path)?;
+
+        trades.extend(config.trade);
+        trades.extend_categories(config.category);
+    }
+    for path in take_workbooks(&mut parser)? {
+        let orders = read_workbooks(&[path], debug)?;
+
+        for mismatch in validate::check_balances(&orders, &trades) {
+            println!(
+                "{} {} {} @date<{}>",
+                "证券余额 mismatch:".bold().yellow(),
+                mismatch.get_code(),
+                mismatch.get_name(),
+                mismatch.get_date(),
+            );
+            if !mismatch.get_account().is_empty() {
+                println!("  account             : {}", mismatch.get_account());
+            }
+            println!(
+                "{}",
+                format!("- expected (workbook) : {}", mismatch.get_expected()).red()
+            );
+            println!(
+                "{}",
+                format!("+ computed            : {}", mismatch.get_computed()).green()
+            );
+            println!(
+                "  source              : {}:{}",
+                mismatch.get_source(),
+                mismatch.get_line()
+            );
+            mismatched += 1;
+        }
     }
-
-    pub fn with_prize(mut self, prize: String) -> Self {
-        self.prize = prize;
-        self
+    if mismatched > 0 {
+        return Err(color_eyre::eyre::eyre!(
+            "证券余额 mismatch of {} rows",
+            mismatched
+        ));
     }
+    println!("no 证券余额 mismatch");
+    Ok(())
+}
 
-    pub fn with_amount(mut self, amount: String) -> Self {
-        self.amount = amount;
-        self
-    }
+/// The options of extra sheets and columns in output, shared by `extract` and `merge`.
+fn add_layout_opts(parser: &mut ForwardParser) -> color_eyre::Result<()> {
+    parser
+        .add_opt("-p=b")?
+        .add_alias("--position-sheet")?
+        .commit()?;
+    parser.add_opt("--holding-sheet=b")?.commit()?;
+    parser.add_opt("--cash-sheet=b")?.commit()?;
+    parser.add_opt("--pivot-sheet=b")?.commit()?;
+    parser.add_opt("--source-comment=b")?.commit()?;
+    parser.add_opt("--with-source=b")?.commit()?;
+    parser.add_opt("--fee-columns=b")?.commit()?;
+    parser.add_opt("--account-column=b")?.commit()?;
+    parser.add_opt("--date-format=s")?.commit()?;
+    parser
+        .add_opt("-g=s")?
+        .add_alias("--group-by")?
+        .set_default_value("flat".into())
+        .commit()?;
+    parser.add_opt("--constant-memory=b")?.commit()?;
+    Ok(())
+}
 
-    pub fn with_owned(mut self, owned: String) -> Self {
-        self.owned = owned;
-        self
-    }
+fn get_layout(parser: &ForwardParser) -> color_eyre::Result<Layout> {
+    let group_by = parser["--group-by"].get_value().as_str().unwrap().clone();
+    let date_format = parser["--date-format"].get_value().as_str().cloned();
 
-    pub fn with_trade(mut self, trade: Trade) -> Self {
-        self.trade = trade;
-        self
+    if let Some(format) = date_format.as_ref() {
+        if !date::is_valid_format(format) {
+            return Err(color_eyre::eyre::eyre!("Invalid date format: {}", format));
+        }
     }
 
-    pub fn get_code(&self) -> &String {
-        &self.code
-    }
+    Ok(Layout::new()
+        .with_group_by(
+            GroupBy::parse(&group_by)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Unknow group by: {}", group_by))?,
+        )
+        .with_position(
+            *parser["--position-sheet"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_holding(
+            *parser["--holding-sheet"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_cash(
+            *parser["--cash-sheet"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_pivot(
+            *parser["--pivot-sheet"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_comments(
+            *parser["--source-comment"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_source(
+            *parser["--with-source"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_fees(
+            *parser["--fee-columns"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_account(
+            *parser["--account-column"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        )
+        .with_date_format(date_format)
+        .with_constant_memory(
+            *parser["--constant-memory"]
+                .get_value()
+                .as_bool()
+                .unwrap_or(&false),
+        ))
+}
 
-    pub fn get_name(&self) -> &String {
-        &self.name
-    }
+type Workbooks = Vec<String>;
 
-    pub fn get_date(&self) -> &String {
-        &self.date
-    }
+/// The positional inputs of `merge`, `report` and `validate`, the directories are expanded to the workbooks in it.
+fn add_workbook_input(parser: &mut ForwardParser) -> color_eyre::Result<()> {
+    let uid = parser.add_opt("input=p!@*")?.commit()?;
 
-    pub fn get_kind(&self) -> &String {
-        &self.kind
-    }
+    parser.add_callback(
+        uid,
+        simple_pos_mut_cb!(move |uid, set: &mut SimpleSet, path, _, _| {
+            let opt = set[uid].as_mut();
+            let mut inputs = opt
+                .get_value_mut()
+                .downcast_mut::<Workbooks>()
+                .map(std::mem::take)
+                .unwrap_or_default();
+
+            // expanded by take_workbooks
+            inputs.push(path.to_owned());
+            Ok(Some(OptValue::from_any(Box::new(inputs))))
+        }),
+    );
+    Ok(())
+}
 
-    pub fn get_count(&self) -> &String {
-        &self.count
+/// Take the positional inputs, fail if an input can not be expanded or matches no workbook.
+fn take_workbooks(parser: &mut ForwardParser) -> color_eyre::Result<Workbooks> {
+    let mut workbooks = vec![];
+
+    for arg in parser["input"]
+        .get_value_mut()
+        .downcast_mut::<Workbooks>()
+        .map(std::mem::take)
+        .unwrap_or_default()
+    {
+        let expanded: Workbooks = expand::expand(&arg, false, None)
+            .map_err(|e| color_eyre::eyre::eyre!("Can not expand input {}: {:?}", arg, e))?
+            .into_iter()
+            .filter(|v| v.as_str() == arg || v.ends_with(".xlsx"))
+            .collect();
+
+        if expanded.is_empty() {
+            return Err(color_eyre::eyre::eyre!("No workbook matched: {}", arg));
+        }
+        workbooks.extend(expanded);
     }
+    Ok(workbooks)
+}
 
-    pub fn get_prize(&self) -> &String {
-        &self.prize
-    }
+fn read_workbooks(paths: &[String], debug: bool) -> color_eyre::Result<Vec<DeliveryOrder>> {
+    let mut orders = vec![];
 
-    pub fn get_amount(&self) -> &String {
-        &self.amount
-    }
+    for path in paths {
+        let rows = tzzb::read_orders(path)?;
 
-    pub fn get_owned(&self) -> &String {
-        &self.owned
+        if debug {
+            eprintln!("got {} rows from {}", rows.len(), path);
+        }
+        orders.extend(rows);
     }
+    Ok(orders)
+}
 
-    pub fn get_trade(&self) -> &Trade {
-        &self.trade
-    }
+/// Return the type of input `path`, the `file_type` of `--type` overrides the detected.
+///
+/// Fail if no broker recognizes the header of `path`.
+fn input_type(file_type: Option<&String>, path: &str) -> color_eyre::Result<String> {
+    Ok(match file_type {
+        // the statements of broker may be saved as spreadsheet
+        Some(file_type) if path.ends_with(".xlsx") && broker::find(file_type).is_none() => {
+            TZZB_TYPE.to_owned()
+        }
+        Some(file_type) => file_type.clone(),
+        // detect the broker of each input, the statements can be mixed
+        None => broker::detect(path)
+            .map(|v| v.get_name().to_owned())
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!("Unknow file type of {}, please set the --type", path)
+            })?,
+    })
+}
+
+async fn receive_orders(
+    rec: Arc<Receiver<Option<DeliveryOrder>>>,
+    counter_reader: Arc<AtomicI32>,
+) -> Vec<DeliveryOrder> {
+    let mut orders = vec![];
+    let mut read_stop_counter = 0;
 
-    pub fn is_valid(&self) -> bool {
-        match self.trade {
-            Trade::Ignore => false,
-            _ => true,
+    loop {
+        if let Some(order) = rec
+            .recv()
+            .await
+            .expect("Unable to receive from read thread")
+        {
+            orders.push(order);
+        } else {
+            read_stop_counter += 1;
+            if read_stop_counter == counter_reader.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
         }
     }
+
+    orders
 }
@@ -0,0 +1,470 @@
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::Trade;
+
+/// How a category change the holdings.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Holdings {
+    None,
+    Increase,
+    Decrease,
+    /// keep the sign of quantity in delivery order
+    Signed,
+}
+
+impl Default for Holdings {
+    fn default() -> Self {
+        Holdings::None
+    }
+}
+
+impl Holdings {
+    pub fn apply(&self, count: i64) -> i64 {
+        match self {
+            Holdings::None => 0,
+            Holdings::Increase => count.abs(),
+            Holdings::Decrease => -count.abs(),
+            Holdings::Signed => count,
+        }
+    }
+}
+
+/// Sign convention of quantities and amounts exported by a broker.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sign {
+    /// sell quantities and outgoing amounts are negative already
+    Signed,
+    /// quantities and amounts are always positive
+    Unsigned,
+}
+
+impl Default for Sign {
+    fn default() -> Self {
+        Sign::Signed
+    }
+}
+
+impl Sign {
+    /// Sign the `amount` by the cash direction of `trade`, the direction of custom trades
+    /// is the sign of its `category`, unknown directions keep as is.
+    pub fn apply(&self, trade: &Trade, category: &Category, amount: Decimal) -> Decimal {
+        match (self, trade) {
+            (Sign::Unsigned, Trade::Buy | Trade::Out | Trade::Fee) => -amount.abs(),
+            (Sign::Unsigned, Trade::Sell | Trade::In | Trade::Dividend | Trade::Interest) => {
+                amount.abs()
+            }
+            (Sign::Unsigned, Trade::Custom(_)) => match category.get_sign() {
+                Some(sign) => amount.abs() * Decimal::from(sign.signum()),
+                None => amount,
+            },
+            _ => amount,
+        }
+    }
+}
+
+/// Category of trade, with the label in output and whether it affects holdings or cash.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Category {
+    label: String,
+    #[serde(default)]
+    holdings: Holdings,
+    #[serde(default)]
+    cash: bool,
+    /// the direction of cash, 1 if received or -1 if paid
+    sign: Option<i64>,
+}
+
+impl Category {
+    pub fn new(label: &str, holdings: Holdings, cash: bool) -> Self {
+        Self {
+            label: label.to_owned(),
+            holdings,
+            cash,
+            sign: None,
+        }
+    }
+
+    pub fn set_sign(&mut self, sign: i64) -> &mut Self {
+        self.sign = Some(sign);
+        self
+    }
+
+    pub fn get_label(&self) -> &String {
+        &self.label
+    }
+
+    pub fn get_holdings(&self) -> Holdings {
+        self.holdings
+    }
+
+    pub fn is_cash(&self) -> bool {
+        self.cash
+    }
+
+    pub fn get_sign(&self) -> Option<i64> {
+        self.sign
+    }
+}
+
+fn default_field() -> String {
+    "业务名称".to_owned()
+}
+
+/// Classification rule, match the column `field` with regex `pattern`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    #[serde(default = "default_field")]
+    field: String,
+    pattern: String,
+    trade: Trade,
+    /// override the holdings effect of category
+    holdings: Option<Holdings>,
+    /// force the sign of 发生金额, 1 or -1
+    sign: Option<i64>,
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+impl Rule {
+    pub fn compile(mut self) -> Result<Self, regex::Error> {
+        self.regex = Some(Regex::new(&self.pattern)?);
+        Ok(self)
+    }
+
+    pub fn get_field(&self) -> &String {
+        &self.field
+    }
+
+    pub fn get_trade(&self) -> &Trade {
+        &self.trade
+    }
+
+    pub fn get_holdings(&self) -> Option<Holdings> {
+        self.holdings
+    }
+
+    pub fn get_sign(&self) -> Option<i64> {
+        self.sign
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex
+            .as_ref()
+            .map(|v| v.is_match(value))
+            .unwrap_or(false)
+    }
+}
+
+/// Map the 业务名称 of delivery order to [`Trade`], the unknown names are ignored.
+#[derive(Debug, Clone)]
+pub struct TradeMap {
+    trades: HashMap<String, Trade>,
+    // 业务名称 of 融资融券 legs
+    margin: HashSet<String>,
+    // 业务名称 spelled by some brokers => the name in mapping
+    aliases: HashMap<String, String>,
+    // name => user defined category
+    categories: HashMap<String, Category>,
+    // evaluated in order before the mapping
+    rules: Vec<Rule>,
+}
+
+impl Default for TradeMap {
+    fn default() -> Self {
+        let mut trades = HashMap::new();
+
+        for (name, trade) in [
+            ("证券卖出", Trade::Sell),
+            ("证券买入", Trade::Buy),
+            // the trade histories of APP, such as 东方财富
+            ("买入", Trade::Buy),
+            ("卖出", Trade::Sell),
+            ("开放基金认购结果", Trade::Buy),
+            ("银证转存", Trade::In),
+            ("银行转存", Trade::In),
+            ("银证转取", Trade::Out),
+            ("银行转取", Trade::Out),
+            // 融资融券, the borrowed cash and shares are not in the position
+            ("融资买入", Trade::Buy),
+            ("卖券还款", Trade::Sell),
+            ("融券卖出", Trade::Custom("融券卖出".to_owned())),
+            ("买券还券", Trade::Custom("买券还券".to_owned())),
+            ("直接还款", Trade::Custom("直接还款".to_owned())),
+            ("偿还融资", Trade::Custom("直接还款".to_owned())),
+            ("直接还券", Trade::Custom("直接还券".to_owned())),
+            ("股息入账", Trade::Dividend),
+            ("红利入账", Trade::Dividend),
+            ("红利发放", Trade::Dividend),
+            ("利息归本", Trade::Interest),
+            ("结息", Trade::Interest),
+            ("佣金扣收", Trade::Fee),
+            ("费用扣收", Trade::Fee),
+            ("红股入账", Trade::BonusShares),
+            ("送股入账", Trade::BonusShares),
+            ("转增股入账", Trade::BonusShares),
+            ("送转股入账", Trade::BonusShares),
+            // position adjustments of 除权, the quantity is the change of holdings
+            ("除权", Trade::Custom("除权除息".to_owned())),
+            ("除权除息", Trade::Custom("除权除息".to_owned())),
+            ("股份拆细", Trade::Custom("份额变动".to_owned())),
+            ("股份合并", Trade::Custom("份额变动".to_owned())),
+            ("股份转入", Trade::Custom("股份转入".to_owned())),
+            ("股份转出", Trade::Custom("股份转出".to_owned())),
+            ("担保品划入", Trade::Custom("担保品划入".to_owned())),
+            ("担保券划入", Trade::Custom("担保品划入".to_owned())),
+            ("担保品划出", Trade::Custom("担保品划出".to_owned())),
+            ("担保券划出", Trade::Custom("担保品划出".to_owned())),
+            ("股息红利差别化扣税", Trade::Custom("红利扣税".to_owned())),
+            ("股息红利差异扣税", Trade::Custom("红利扣税".to_owned())),
+            ("红利差异税扣税", Trade::Custom("红利扣税".to_owned())),
+            ("股息红利税补缴", Trade::Custom("红利扣税".to_owned())),
+            ("基金转换转入", Trade::Custom("份额转入".to_owned())),
+            ("基金转换转出", Trade::Custom("份额转出".to_owned())),
+            ("基金转换", Trade::Custom("份额变动".to_owned())),
+            ("份额合并", Trade::Custom("份额变动".to_owned())),
+            ("份额折算", Trade::Custom("份额变动".to_owned())),
+            ("份额拆分", Trade::Custom("份额变动".to_owned())),
+            ("基金份额折算", Trade::Custom("份额变动".to_owned())),
+            ("要约收购", Trade::Custom("要约收购".to_owned())),
+            ("要约收购过户", Trade::Custom("要约收购".to_owned())),
+            ("股份回购", Trade::Custom("回购注销".to_owned())),
+            ("回购注销", Trade::Custom("回购注销".to_owned())),
+            ("股份回购注销", Trade::Custom("回购注销".to_owned())),
+            ("配债缴款", Trade::Custom("配债缴款".to_owned())),
+            ("配售缴款", Trade::Custom("配债缴款".to_owned())),
+            ("配债入账", Trade::Custom("配债入账".to_owned())),
+            ("可转债入账", Trade::Custom("配债入账".to_owned())),
+            ("债券配售入账", Trade::Custom("配债入账".to_owned())),
+            ("新股申购", Trade::Custom("新股申购".to_owned())),
+            ("申购配号", Trade::Custom("新股配号".to_owned())),
+            ("配号", Trade::Custom("新股配号".to_owned())),
+            ("中签缴款", Trade::Custom("中签缴款".to_owned())),
+            ("新股中签", Trade::Custom("中签缴款".to_owned())),
+            ("中签扣款", Trade::Custom("中签缴款".to_owned())),
+            ("新股入帐", Trade::Custom("新股入账".to_owned())),
+            ("新股入账", Trade::Custom("新股入账".to_owned())),
+            ("新股上市", Trade::Custom("新股入账".to_owned())),
+            // noise rows, can be output by `remove` them from `[ignore]` of config
+            ("指定交易", Trade::Ignore),
+            ("撤销指定", Trade::Ignore),
+            ("托管转入", Trade::Ignore),
+        ] {
+            trades.insert(name.to_owned(), trade);
+        }
+
+        let margin = [
+            "融资买入",
+            "卖券还款",
+            "融券卖出",
+            "买券还券",
+            "直接还款",
+            "偿还融资",
+            "直接还券",
+        ]
+        .map(|v| v.to_owned())
+        .into_iter()
+        .collect();
+        let mut categories = HashMap::new();
+
+        for (name, holdings, cash) in [
+            // collateral moved between 普通 and 信用 account, an internal position transfer
+            // which is paired by a reversed row in the other account
+            ("担保品划入", Holdings::Increase, false),
+            ("担保品划出", Holdings::Decrease, false),
+            // quantity of one code converted into another's, each code has its own row
+            ("份额转入", Holdings::Increase, false),
+            ("份额转出", Holdings::Decrease, false),
+            ("份额变动", Holdings::Signed, false),
+            ("除权除息", Holdings::Signed, false),
+            // shares moved between accounts or brokers
+            ("股份转入", Holdings::Increase, false),
+            ("股份转出", Holdings::Decrease, false),
+            // shares removed and paid by cash, like a sell
+            ("要约收购", Holdings::Decrease, true),
+            ("回购注销", Holdings::Decrease, true),
+            // the payment of allotment is under a temporary code, the bond position
+            // is created when the bond is credited
+            ("配债缴款", Holdings::None, true),
+            ("配债入账", Holdings::Increase, false),
+            // the shares sold short are borrowed, and returned by the shares bought
+            ("融券卖出", Holdings::None, true),
+            ("买券还券", Holdings::None, true),
+            ("直接还款", Holdings::None, true),
+            // the shares held are returned to the broker
+            ("直接还券", Holdings::Decrease, false),
+            // the quantity of 申购 and 配号 rows is the numbers applied, not the shares,
+            // the shares won are paid first and credited at listing, like the bonds
+            ("新股申购", Holdings::None, false),
+            ("新股配号", Holdings::None, false),
+            ("中签缴款", Holdings::None, true),
+            ("新股入账", Holdings::Increase, false),
+        ] {
+            categories.insert(name.to_owned(), Category::new(name, holdings, cash));
+        }
+        // tax of dividend deducted months after the sell, keep the code of original
+        // security so it can be attached to the dividend, but only cash is changed
+        categories.insert(
+            "红利扣税".to_owned(),
+            Category::new("股息红利差异扣税", Holdings::None, true),
+        );
+        // the cash direction, signs the amounts of the brokers not signed
+        for (name, sign) in [
+            ("要约收购", 1),
+            ("回购注销", 1),
+            ("配债缴款", -1),
+            ("融券卖出", 1),
+            ("买券还券", -1),
+            ("直接还款", -1),
+            ("中签缴款", -1),
+            ("红利扣税", -1),
+        ] {
+            if let Some(category) = categories.get_mut(name) {
+                category.set_sign(sign);
+            }
+        }
+        Self {
+            trades,
+            margin,
+            aliases: HashMap::new(),
+            categories,
+            rules: vec![],
+        }
+    }
+}
+
+impl TradeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: String, trade: Trade) -> Option<Trade> {
+        self.trades.insert(name, trade)
+    }
+
+    pub fn extend(&mut self, trades: HashMap<String, Trade>) {
+        self.trades.extend(trades);
+    }
+
+    /// Add the aliases of 业务名称, such as "证券买入(普通)" = "证券买入".
+    pub fn extend_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.aliases.extend(aliases);
+    }
+
+    /// The name in mapping of 业务名称 `name`.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map(|v| v.as_str()).unwrap_or(name)
+    }
+
+    pub fn extend_categories(&mut self, categories: HashMap<String, Category>) {
+        self.categories.extend(categories);
+    }
+
+    /// Ignore the rows of business `name`.
+    pub fn add_ignore(&mut self, name: String) {
+        self.trades.insert(name, Trade::Ignore);
+    }
+
+    /// Output the rows of business `name` which is ignored, as a trade named by itself.
+    pub fn remove_ignore(&mut self, name: String) {
+        if self.get(&name) == Trade::Ignore {
+            self.trades.insert(name.clone(), Trade::Custom(name));
+        }
+    }
+
+    pub fn add_rules(&mut self, rules: Vec<Rule>) -> Result<(), regex::Error> {
+        for rule in rules {
+            self.rules.push(rule.compile()?);
+        }
+        Ok(())
+    }
+
+    /// Return the first rule matched, `value_of` return the value of given column.
+    pub fn match_rule<'a, F>(&self, value_of: F) -> Option<&Rule>
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        self.rules.iter().find(|rule| {
+            value_of(rule.get_field())
+                .map(|v| rule.is_match(v))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Return true if the business `name` is a leg of 融资融券.
+    pub fn is_margin(&self, name: &str) -> bool {
+        self.margin.contains(self.resolve(name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.trades.contains_key(self.resolve(name))
+    }
+
+    pub fn get(&self, name: &str) -> Trade {
+        self.trades
+            .get(self.resolve(name))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn category(&self, trade: &Trade) -> Category {
+        match trade {
+            Trade::Buy => Category::new(trade.label(), Holdings::Increase, true),
+            Trade::Sell => Category::new(trade.label(), Holdings::Decrease, true),
+            Trade::In | Trade::Out => Category::new(trade.label(), Holdings::None, true),
+            Trade::Dividend | Trade::Interest | Trade::Fee => {
+                Category::new(trade.label(), Holdings::None, true)
+            }
+            Trade::BonusShares => Category::new(trade.label(), Holdings::Increase, false),
+            // the ignored rows are not output, but still count the holdings (e.g. 托管转入)
+            Trade::Ignore => Category::new(trade.label(), Holdings::Increase, false),
+            Trade::Custom(name) => self
+                .categories
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| Category::new(name, Holdings::None, false)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_unsigned_amounts() {
+        let trades = TradeMap::new();
+        let amount = Decimal::new(1000, 0);
+        let apply = |trade: Trade| Sign::Unsigned.apply(&trade, &trades.category(&trade), amount);
+
+        assert_eq!(apply(Trade::Buy), -amount);
+        assert_eq!(apply(Trade::Fee), -amount);
+        assert_eq!(apply(Trade::Sell), amount);
+        assert_eq!(apply(Trade::Dividend), amount);
+        assert_eq!(apply(Trade::Custom("中签缴款".to_owned())), -amount);
+        assert_eq!(apply(Trade::Custom("要约收购".to_owned())), amount);
+        // the direction of category without sign is unknown
+        assert_eq!(apply(Trade::Custom("份额变动".to_owned())), amount);
+    }
+
+    #[test]
+    fn sign_signed_amounts() {
+        let trades = TradeMap::new();
+        let amount = Decimal::new(-1000, 0);
+
+        for trade in [
+            Trade::Buy,
+            Trade::Sell,
+            Trade::Custom("中签缴款".to_owned()),
+        ] {
+            assert_eq!(
+                Sign::Signed.apply(&trade, &trades.category(&trade), amount),
+                amount
+            );
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::broker::{BrokerParser, FileOrders};
+use crate::error::ParseError;
+use crate::htsc::Context;
+use crate::overseas::{self, Record};
+use crate::{DeliveryOrder, Trade};
+
+// the column names changed between versions of the app
+const SIDE_KEYS: [&'static str; 2] = ["Side", "Direction"];
+const CODE_KEYS: [&'static str; 2] = ["Symbol", "Code"];
+const COUNT_KEYS: [&'static str; 3] = ["Fill Qty", "Filled Qty", "Qty"];
+const PRICE_KEYS: [&'static str; 4] = ["Fill Price", "Filled Price", "Avg Price", "Price"];
+const AMOUNT_KEYS: [&'static str; 3] = ["Fill Amount", "Filled Amount", "Amount"];
+const DATE_KEYS: [&'static str; 4] = ["Fill Time", "Filled Time", "Order Time", "Time"];
+const FEE_KEYS: [&'static str; 2] = ["Total Fees", "Fees"];
+
+/// The trade history export of moomoo, a UTF-8 CSV.
+#[derive(Debug)]
+pub struct Moomoo;
+
+impl BrokerParser for Moomoo {
+    fn get_name(&self) -> &'static str {
+        crate::MOOMOO_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        [&SIDE_KEYS[..], &CODE_KEYS[..], &COUNT_KEYS[..]]
+            .iter()
+            .all(|keys| keys.iter().any(|key| line.contains(key)))
+    }
+
+    fn get_encoding(&self) -> &'static Encoding {
+        UTF_8
+    }
+
+    fn read_file<'a>(&'a self, ctx: &'a mut Context, path: &'a str) -> Option<FileOrders<'a>> {
+        Some(Box::pin(overseas::read_orders(ctx, path, to_order, true)))
+    }
+}
+
+/// Convert a row of moomoo trade history export, the rows are newest first.
+///
+/// The orders which are not filled are skipped.
+pub fn to_order(record: &Record) -> Result<Option<DeliveryOrder>, ParseError> {
+    let side = record.get(&SIDE_KEYS).unwrap_or_default().to_lowercase();
+    // e.g. `Sell Short` and `Buy to Cover`
+    let trade = if side.starts_with("buy") {
+        Trade::Buy
+    } else if side.starts_with("sell") {
+        Trade::Sell
+    } else {
+        return Ok(None);
+    };
+    let count = match overseas::money_of(record, &COUNT_KEYS)? {
+        Some(count) if !count.is_zero() => count,
+        _ => return Ok(None),
+    };
+    let (date, code) = match (
+        record.get(&DATE_KEYS).and_then(overseas::parse_date),
+        record.get(&CODE_KEYS),
+    ) {
+        (Some(date), Some(code)) => (date, code),
+        _ => return Ok(None),
+    };
+    let name = record.get(&["Name"]).unwrap_or(code);
+    let price = match overseas::money_of(record, &PRICE_KEYS)? {
+        Some(price) => price,
+        None => return Ok(None),
+    };
+    let amount = overseas::money_of(record, &AMOUNT_KEYS)?
+        .unwrap_or(count.abs() * price)
+        .abs();
+    let fees = overseas::money_of(record, &FEE_KEYS)?;
+    let fee = fees.unwrap_or_default().abs();
+    let amount = match trade {
+        Trade::Sell => amount - fee,
+        _ => amount + fee,
+    };
+    let order = overseas::trade_order(trade, date, code, name, count, price, Some(amount));
+
+    Ok(Some(order.with_fee(fees.map(|_| fee))))
+}
@@ -0,0 +1,24 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::str::FromStr;
+
+/// Parse the number of delivery order, the scale of `value` is kept, e.g. `12.300`.
+pub fn parse_number(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .ok()
+}
+
+/// Render the number `value`, empty if it is not given.
+pub fn format_number(value: Option<Decimal>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Round half away from zero to `places`, the trailing zeros are kept, e.g. `5.00`.
+pub fn round(value: Decimal, places: u32) -> Decimal {
+    let mut value = value.round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero);
+
+    value.rescale(places);
+    value
+}
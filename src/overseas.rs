@@ -0,0 +1,213 @@
+use async_std::fs::File;
+use chrono::NaiveDate;
+use encoding_rs::UTF_8;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::date;
+use crate::error::{Error, ParseError};
+use crate::htsc::Context;
+use crate::number;
+use crate::reader::LineReader;
+use crate::tokenizer::Dialect;
+use crate::{DeliveryOrder, Trade};
+
+const US_FORMATS: [&'static str; 2] = ["%m/%d/%Y", "%b %d, %Y"];
+
+/// One row of the trade history exported by overseas brokers, column name => value.
+#[derive(Debug, Default)]
+pub struct Record {
+    line: usize,
+    content: String,
+    values: HashMap<String, String>,
+}
+
+impl Record {
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    /// The line of record as is, for the error report.
+    pub fn get_content(&self) -> &String {
+        &self.content
+    }
+
+    /// Value of the first column in `keys` which is not empty, brokers rename the columns now and then.
+    pub fn get(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .filter_map(|key| self.values.get(*key))
+            .map(|v| v.trim())
+            .find(|v| !v.is_empty())
+    }
+}
+
+/// Read the records of a CSV export, the first line is the header.
+///
+/// A quoted field may span multiple lines, e.g. the description of Robinhood.
+pub async fn read_records(path: &str) -> std::io::Result<Vec<Record>> {
+    let mut reader = LineReader::new(File::open(path).await?, UTF_8);
+    let dialect = Dialect::new(',', '"');
+    let mut title: Vec<String> = vec![];
+    let mut records = vec![];
+    let mut pending = String::new();
+    let mut start = 0;
+
+    while let Some(line) = reader.read_line().await? {
+        if pending.is_empty() {
+            start = reader.get_line_no();
+        } else {
+            pending.push('\n');
+        }
+        pending.push_str(&line);
+        // wait the rest lines of quoted field
+        if pending.matches(dialect.get_quote()).count() % 2 == 1 {
+            continue;
+        }
+        let line = std::mem::take(&mut pending);
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = dialect.split(&line);
+
+        if title.is_empty() {
+            title = fields.into_iter().map(|v| v.trim().to_owned()).collect();
+        } else {
+            records.push(Record {
+                line: start,
+                values: title.iter().cloned().zip(fields.into_iter()).collect(),
+                content: line,
+            });
+        }
+    }
+
+    Ok(records)
+}
+
+/// Parse the money like `$1,234.56` or `($1,234.56)`, the latter is negative.
+pub fn parse_money(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let value = number::parse_number(&value.replace(['$', ',', ' '], ""))?;
+
+    Some(if negative { -value } else { value })
+}
+
+/// Parse the money of the first column in `keys`, error if it is not a number.
+pub fn money_of(record: &Record, keys: &[&str]) -> Result<Option<Decimal>, ParseError> {
+    match record.get(keys) {
+        Some(value) => parse_money(value)
+            .map(Some)
+            .ok_or_else(|| ParseError::Number {
+                column: keys[0].to_owned(),
+                value: value.to_owned(),
+            }),
+        None => Ok(None),
+    }
+}
+
+/// Parse the date or time of US style, the time part is dropped.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    let first = value.split_whitespace().next()?;
+
+    date::parse_date(first).or_else(|| {
+        US_FORMATS.iter().find_map(|fmt| {
+            NaiveDate::parse_from_str(first, fmt)
+                .or_else(|_| NaiveDate::parse_and_remainder(value, fmt).map(|(v, _)| v))
+                .ok()
+        })
+    })
+}
+
+/// Sort the orders chronologically and fill the 证券余额 by a running count per code.
+pub fn fill_owned(orders: &mut Vec<DeliveryOrder>) {
+    // fractional shares are common
+    let mut count: HashMap<String, Decimal> = HashMap::new();
+
+    // stable, keep the order of rows in same day
+    orders.sort_by_key(|v| v.get_date().clone());
+    for order in orders.iter_mut() {
+        let owned = count.entry(order.get_code().clone()).or_default();
+
+        *owned += order.get_count();
+        order.set_owned(Some(owned.normalize()));
+    }
+}
+
+/// Read the orders of a CSV export with `to_order`, sorted chronologically.
+///
+/// Set `newest_first` if the rows of export is in reverse chronological order. The
+/// records can't be converted are handled by [`Context::handle_malformed`].
+pub async fn read_orders(
+    ctx: &mut Context,
+    path: &str,
+    to_order: fn(&Record) -> Result<Option<DeliveryOrder>, ParseError>,
+    newest_first: bool,
+) -> Result<Vec<DeliveryOrder>, Error> {
+    let mut records = read_records(path).await?;
+    let mut orders = vec![];
+
+    if newest_first {
+        records.reverse();
+    }
+    for record in records.iter() {
+        match to_order(record) {
+            Ok(Some(order)) => orders.push(
+                order
+                    .with_source(path.to_owned())
+                    .with_line(record.get_line()),
+            ),
+            Ok(None) => {}
+            Err(e) => {
+                ctx.set_line_no(record.get_line())
+                    .handle_malformed(e, record.get_content())?;
+            }
+        }
+    }
+    fill_owned(&mut orders);
+
+    Ok(orders)
+}
+
+/// Build the order of a cash movement, which is signed already.
+pub fn cash_order(trade: Trade, date: NaiveDate, name: &str, amount: Decimal) -> DeliveryOrder {
+    DeliveryOrder::default()
+        .with_date(date.format("%Y%m%d").to_string())
+        .with_name(name.to_owned())
+        .with_kind(trade.label().to_owned())
+        .with_count(Decimal::ZERO)
+        .with_amount(Some(number::round(amount, 2)))
+        .with_trade(trade)
+}
+
+/// Build the order of a buy or sell, the `amount` should include the fees.
+pub fn trade_order(
+    trade: Trade,
+    date: NaiveDate,
+    code: &str,
+    name: &str,
+    count: Decimal,
+    price: Decimal,
+    amount: Option<Decimal>,
+) -> DeliveryOrder {
+    let count = count.abs();
+    let amount = amount.map(|v| v.abs()).unwrap_or(count * price);
+    let (count, amount) = match trade {
+        Trade::Sell => (-count, amount),
+        _ => (count, -amount),
+    };
+
+    DeliveryOrder::default()
+        .with_date(date.format("%Y%m%d").to_string())
+        .with_code(code.to_owned())
+        .with_name(name.to_owned())
+        .with_kind(trade.label().to_owned())
+        .with_count(count)
+        .with_prize(Some(price))
+        .with_amount(Some(number::round(amount, 2)))
+        .with_trade(trade)
+}
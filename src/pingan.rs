@@ -0,0 +1,43 @@
+use crate::broker::BrokerParser;
+use crate::date;
+
+/// The delivery order exported by 平安证券.
+///
+/// The 业务名称 are suffixed with 清算, e.g. 证券买入清算, and the dates are
+/// in `YYYY/MM/DD`.
+#[derive(Debug)]
+pub struct Pingan;
+
+impl BrokerParser for Pingan {
+    fn get_name(&self) -> &'static str {
+        crate::PINGAN_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.contains("交收日期") && line.contains("业务名称")
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        match title {
+            "交收日期" | "成交日期" => "发生日期",
+            "清算金额" => "发生金额",
+            "股份余额" => "证券数量",
+            title => title,
+        }
+        .to_owned()
+    }
+
+    fn map_value(&self, title: &str, value: &str) -> String {
+        match title {
+            "发生日期" => date::parse_date(value)
+                .map(|v| v.format("%Y%m%d").to_string())
+                .unwrap_or_else(|| value.to_owned()),
+            // 证券买入清算 => 买入
+            "业务名称" => match value.strip_suffix("清算") {
+                Some(name) => name.strip_prefix("证券").unwrap_or(name).to_owned(),
+                None => value.to_owned(),
+            },
+            _ => value.to_owned(),
+        }
+    }
+}
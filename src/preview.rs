@@ -0,0 +1,43 @@
+use comfy_table::{Cell, CellAlignment, Table};
+use std::io::{BufRead, Write};
+
+use crate::htsc;
+use crate::number;
+use crate::DeliveryOrder;
+
+/// Print the first `rows` orders in the same columns as the workbook.
+pub fn print(orders: &[DeliveryOrder], rows: usize) {
+    let mut table = Table::new();
+
+    table.set_header(htsc::Context::gen_title());
+    for order in orders.iter().take(rows) {
+        table.add_row(vec![
+            Cell::new(order.get_date()),
+            Cell::new(order.get_code()),
+            Cell::new(order.get_name()),
+            Cell::new(order.get_kind()),
+            Cell::new(order.get_count()).set_alignment(CellAlignment::Right),
+            Cell::new(number::format_number(order.get_prize())).set_alignment(CellAlignment::Right),
+            Cell::new(number::format_number(order.get_amount()))
+                .set_alignment(CellAlignment::Right),
+            Cell::new(number::format_number(order.get_owned())).set_alignment(CellAlignment::Right),
+        ]);
+    }
+    eprintln!("{}", table);
+    eprintln!(
+        "preview {} of {} rows",
+        rows.min(orders.len()),
+        orders.len()
+    );
+}
+
+/// Ask user whether to continue, only `y` or `yes` means yes.
+pub fn confirm(prompt: &str) -> std::io::Result<bool> {
+    let mut answer = String::new();
+
+    eprint!("{} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,168 @@
+use async_std::fs::File;
+use async_std::io::{prelude::BufReadExt, BufReader, Read, ReadExt};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::sync::OnceLock;
+
+/// Lines of a statement, read from a text file or the rows of a spreadsheet.
+pub enum Lines {
+    // file on disk, or entry of zip file
+    Text(LineReader<Box<dyn Read + Unpin + Send>>),
+    Rows(std::vec::IntoIter<(usize, String)>),
+}
+
+impl Lines {
+    /// Return the next line and its line number.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<(usize, String)>> {
+        match self {
+            Self::Text(reader) => Ok(reader
+                .read_line()
+                .await?
+                .map(|line| (reader.get_line_no(), line))),
+            Self::Rows(rows) => Ok(rows.next()),
+        }
+    }
+}
+
+/// The input path stands for standard input.
+pub const STDIN_PATH: &'static str = "-";
+
+static STDIN: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Read the whole standard input, it is cached for reading multiple times
+/// such as detecting the broker and pre-scanning the dates.
+pub fn read_stdin() -> std::io::Result<&'static [u8]> {
+    if let Some(content) = STDIN.get() {
+        return Ok(content);
+    }
+    let mut content = vec![];
+
+    std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut content)?;
+    Ok(STDIN.get_or_init(|| content))
+}
+
+// bytes read for guessing the encoding of file
+const SNIFF_BYTES: u64 = 16 * 1024;
+
+/// Guess the encoding of `head`, the first bytes of a file.
+///
+/// The byte order mark wins, then UTF-16 without BOM is recognized by the zero
+/// bytes of ASCII characters. Return [`UTF_8`] if it has non-ASCII characters and
+/// is valid UTF-8, otherwise None and let caller decide, such as GBK.
+pub fn sniff(head: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return Some(encoding);
+    }
+    let even = head.iter().step_by(2).filter(|v| **v == 0).count();
+    let odd = head.iter().skip(1).step_by(2).filter(|v| **v == 0).count();
+
+    // most characters of statement are digits and delimiters
+    if odd > head.len() / 4 && even == 0 {
+        return Some(UTF_16LE);
+    }
+    if even > head.len() / 4 && odd == 0 {
+        return Some(UTF_16BE);
+    }
+    if head.is_ascii() {
+        return None;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => Some(UTF_8),
+        // the last character may be truncated
+        Err(e) if e.error_len().is_none() => Some(UTF_8),
+        Err(_) => None,
+    }
+}
+
+/// Guess the encoding of file `path`, see [`sniff`].
+pub async fn sniff_file(path: &str) -> std::io::Result<Option<&'static Encoding>> {
+    let mut head = vec![];
+
+    File::open(path)
+        .await?
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut head)
+        .await?;
+    Ok(sniff(&head))
+}
+
+/// Read decoded lines from a delivery order file.
+///
+/// The byte order mark of the first line is removed and switch the encoding,
+/// and the line endings (`\n` or `\r\n`) are not included in the line.
+#[derive(Debug)]
+pub struct LineReader<R> {
+    reader: BufReader<R>,
+    buffer: Vec<u8>,
+    encoding: &'static Encoding,
+    line_no: usize,
+}
+
+impl<R: Read + Unpin> LineReader<R> {
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            reader: BufReader::new(inner),
+            buffer: Vec::with_capacity(4096),
+            encoding,
+            line_no: 0,
+        }
+    }
+
+    pub fn get_encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Line number of the last line read, start from 1.
+    pub fn get_line_no(&self) -> usize {
+        self.line_no
+    }
+
+    pub async fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buffer.clear();
+        if self.reader.read_until(0x0a as u8, &mut self.buffer).await? == 0 {
+            return Ok(None);
+        }
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            self.read_utf16_line().await?;
+        }
+        let mut bytes = &self.buffer[..];
+
+        if self.line_no == 0 {
+            if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+                self.encoding = encoding;
+                bytes = &bytes[bom_len..];
+            }
+        }
+        self.line_no += 1;
+
+        let (line, _) = self.encoding.decode_without_bom_handling(bytes);
+
+        Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_owned()))
+    }
+
+    /// Complete the line of UTF-16, the byte `0x0a` may be part of other characters.
+    async fn read_utf16_line(&mut self) -> std::io::Result<()> {
+        let newline: [u8; 2] = if self.encoding == UTF_16LE {
+            [0x0a, 0]
+        } else {
+            [0, 0x0a]
+        };
+
+        loop {
+            if self.buffer.len() % 2 == 1 {
+                let mut byte = [0u8; 1];
+
+                if self.reader.read(&mut byte).await? == 0 {
+                    break;
+                }
+                self.buffer.push(byte[0]);
+            }
+            if self.buffer.ends_with(&newline) {
+                break;
+            }
+            if self.reader.read_until(0x0a as u8, &mut self.buffer).await? == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,515 @@
+use chrono::NaiveDate;
+use comfy_table::{Cell, CellAlignment, Table};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::date;
+use crate::number;
+use crate::{DeliveryOrder, Trade};
+
+// a loss is washed if the code is bought again in the days after the sell
+const WASH_SALE_DAYS: i64 = 30;
+// the category of the tax withheld from dividends
+const WITHHOLDING: &'static str = "红利扣税";
+
+/// Profit and loss of a code, the cost of sold shares is valued by the average cost.
+#[derive(Debug, Default, Clone)]
+pub struct Profit {
+    code: String,
+    name: String,
+    bought: Decimal,
+    sold: Decimal,
+    income: Decimal,
+    realized: Decimal,
+    count: i64,
+    cost: Decimal,
+    // date and loss of the sells which may be washed by a later buy
+    losses: Vec<(NaiveDate, Decimal)>,
+    washed: Decimal,
+}
+
+impl Profit {
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Total paid amount of buy.
+    pub fn get_bought(&self) -> Decimal {
+        self.bought
+    }
+
+    /// Total received amount of sell.
+    pub fn get_sold(&self) -> Decimal {
+        self.sold
+    }
+
+    /// Cash in or out of the code beside trades, such as dividends and taxes.
+    pub fn get_income(&self) -> Decimal {
+        self.income
+    }
+
+    /// Realized profit of the sold shares, the income is not included.
+    pub fn get_realized(&self) -> Decimal {
+        self.realized
+    }
+
+    pub fn get_count(&self) -> i64 {
+        self.count
+    }
+
+    /// Cost of the shares still held.
+    pub fn get_cost(&self) -> Decimal {
+        self.cost
+    }
+
+    /// Loss of the sells followed by a buy of the code in 30 days, the wash sales of US tax.
+    ///
+    /// Only the overseas codes are checked, see [`is_overseas`].
+    pub fn get_washed(&self) -> Decimal {
+        self.washed
+    }
+
+    fn add(&mut self, order: &DeliveryOrder) {
+        let count = order.get_count().trunc().to_i64().unwrap_or(0).abs();
+        let amount = order.get_amount().unwrap_or_default();
+
+        match order.get_trade() {
+            Trade::Buy => {
+                // valued by the price if there is no amount
+                let paid = if !amount.is_zero() {
+                    amount.abs()
+                } else {
+                    Decimal::from(count) * order.get_prize().unwrap_or_default()
+                };
+
+                self.bought += paid;
+                self.cost += paid;
+                self.count += count;
+                if let Some(date) = date::parse_date(order.get_date()) {
+                    for (sold, loss) in std::mem::take(&mut self.losses) {
+                        let days = (date - sold).num_days();
+
+                        if (0..=WASH_SALE_DAYS).contains(&days) {
+                            self.washed += loss;
+                        } else if days < 0 {
+                            self.losses.push((sold, loss));
+                        }
+                    }
+                }
+            }
+            Trade::Sell => {
+                let average = if self.count != 0 {
+                    self.cost / Decimal::from(self.count)
+                } else {
+                    Decimal::ZERO
+                };
+                let sold = average * Decimal::from(count.min(self.count.max(0)));
+                let realized = amount.abs() - sold;
+
+                self.sold += amount.abs();
+                self.realized += realized;
+                if realized.is_sign_negative() && is_overseas(order.get_code()) {
+                    if let Some(date) = date::parse_date(order.get_date()) {
+                        self.losses.push((date, realized));
+                    }
+                }
+                self.cost -= sold;
+                self.count -= count;
+                if self.count <= 0 {
+                    self.cost = Decimal::ZERO;
+                }
+            }
+            Trade::BonusShares => {
+                // the average cost is diluted
+                self.count += count;
+            }
+            Trade::In | Trade::Out | Trade::Dividend | Trade::Interest | Trade::Fee => {
+                self.income += amount;
+            }
+            _ => {}
+        }
+        self.name = order.get_name().clone();
+    }
+}
+
+/// Profit and loss of each code in `orders`, the rows without code are skipped.
+pub fn profits(orders: &[DeliveryOrder]) -> Vec<Profit> {
+    let mut profits: BTreeMap<String, Profit> = BTreeMap::new();
+
+    for order in orders.iter().filter(|v| !v.get_code().is_empty()) {
+        profits
+            .entry(order.get_code().clone())
+            .or_insert_with(|| Profit {
+                code: order.get_code().clone(),
+                ..Profit::default()
+            })
+            .add(order);
+    }
+
+    profits.into_values().collect()
+}
+
+/// Render the profits as a text table, followed by the totals.
+pub fn render(profits: &[Profit]) -> String {
+    let mut table = Table::new();
+    let cell =
+        |value: Decimal| Cell::new(format!("{:.2}", value)).set_alignment(CellAlignment::Right);
+
+    table.set_header(vec![
+        "证券代码",
+        "证券名称",
+        "买入金额",
+        "卖出金额",
+        "已实现盈亏",
+        "红利及其他",
+        "持仓数量",
+        "持仓成本",
+        "洗售亏损",
+    ]);
+    for profit in profits {
+        table.add_row(vec![
+            Cell::new(&profit.code),
+            Cell::new(&profit.name),
+            cell(profit.bought),
+            cell(profit.sold),
+            cell(profit.realized),
+            cell(profit.income),
+            Cell::new(profit.count).set_alignment(CellAlignment::Right),
+            cell(profit.cost),
+            // only the codes have wash sales are flagged
+            if profit.washed.is_zero() {
+                Cell::new("")
+            } else {
+                cell(profit.washed)
+            },
+        ]);
+    }
+    format!(
+        "{}\ntotal realized = {:.2}, total income = {:.2}, total washed = {:.2}",
+        table,
+        profits.iter().map(|v| v.realized).sum::<Decimal>(),
+        profits.iter().map(|v| v.income).sum::<Decimal>(),
+        profits.iter().map(|v| v.washed).sum::<Decimal>()
+    )
+}
+
+/// Return true if `code` is not a code of A-share, which has 6 digits.
+///
+/// The codes of HK have 5 digits, and the tickers of US are letters.
+pub fn is_overseas(code: &str) -> bool {
+    !code.is_empty() && !(code.len() == 6 && code.chars().all(|v| v.is_ascii_digit()))
+}
+
+/// Return the currency of overseas `code`, HKD for the codes of HK and USD for the tickers.
+pub fn currency_of(code: &str) -> &'static str {
+    if code.chars().all(|v| v.is_ascii_digit()) {
+        "HKD"
+    } else {
+        "USD"
+    }
+}
+
+/// Overseas income of a year in RMB, the items of 境外所得 in the declaration of 个人所得税.
+#[derive(Debug, Default, Clone)]
+pub struct ForeignIncome {
+    year: String,
+    dividend: Decimal,
+    realized: Decimal,
+    withheld: Decimal,
+}
+
+impl ForeignIncome {
+    pub fn get_year(&self) -> &String {
+        &self.year
+    }
+
+    /// 利息、股息、红利所得, the dividends received in the year.
+    pub fn get_dividend(&self) -> Decimal {
+        self.dividend
+    }
+
+    /// 财产转让所得, the realized profit of the sells in the year.
+    pub fn get_realized(&self) -> Decimal {
+        self.realized
+    }
+
+    /// The tax withheld abroad from the dividends, the 红利扣税 rows of the overseas codes.
+    pub fn get_withheld(&self) -> Decimal {
+        self.withheld
+    }
+
+    /// Tax of 20%, the loss of transfer is not deducted from the dividends.
+    pub fn get_tax(&self) -> Decimal {
+        (self.dividend.max(Decimal::ZERO) + self.realized.max(Decimal::ZERO)) * Decimal::new(2, 1)
+    }
+
+    /// Tax payable after the credit of withheld tax, which is limited to the tax of the dividends.
+    pub fn get_payable(&self) -> Decimal {
+        let credit = self
+            .withheld
+            .min(self.dividend.max(Decimal::ZERO) * Decimal::new(2, 1));
+
+        self.get_tax() - credit
+    }
+}
+
+/// Dividends, realized profit and withheld tax of the overseas codes in `orders` by year.
+///
+/// The orders are processed chronologically, and the cost is valued as [`profits`]. The
+/// amounts are converted to RMB by `rates`, currency => year => RMB per unit, return the
+/// currency and year if the rate is missing.
+pub fn foreign_incomes(
+    orders: &[DeliveryOrder],
+    rates: &HashMap<String, HashMap<String, Decimal>>,
+) -> Result<Vec<ForeignIncome>, (String, String)> {
+    let mut orders: Vec<&DeliveryOrder> = orders
+        .iter()
+        .filter(|v| is_overseas(v.get_code()))
+        .collect();
+    let mut profits: HashMap<String, Profit> = HashMap::new();
+    let mut incomes: BTreeMap<String, ForeignIncome> = BTreeMap::new();
+
+    // stable, keep the order of rows in same day
+    orders.sort_by_key(|v| v.get_date().clone());
+    for order in orders {
+        let profit = profits.entry(order.get_code().clone()).or_default();
+        let realized = profit.realized;
+        let year = order.get_date().chars().take(4).collect::<String>();
+        let currency = currency_of(order.get_code());
+        let rate = rates
+            .get(currency)
+            .and_then(|v| v.get(&year))
+            .copied()
+            .ok_or_else(|| (currency.to_owned(), year.clone()))?;
+        let income = incomes
+            .entry(year.clone())
+            .or_insert_with(|| ForeignIncome {
+                year,
+                ..ForeignIncome::default()
+            });
+
+        profit.add(order);
+        income.realized += (profit.realized - realized) * rate;
+        match order.get_trade() {
+            Trade::Dividend => income.dividend += order.get_amount().unwrap_or_default() * rate,
+            Trade::Custom(name) if name == WITHHOLDING => {
+                income.withheld -= order.get_amount().unwrap_or_default() * rate
+            }
+            _ => {}
+        }
+    }
+
+    Ok(incomes.into_values().collect())
+}
+
+/// Render the overseas incomes as a text table.
+pub fn render_foreign(incomes: &[ForeignIncome]) -> String {
+    let mut table = Table::new();
+    let cell =
+        |value: Decimal| Cell::new(format!("{:.2}", value)).set_alignment(CellAlignment::Right);
+
+    table.set_header(vec![
+        "所得年度",
+        "利息、股息、红利所得",
+        "财产转让所得",
+        "应纳税额",
+        "境外已纳税额",
+        "应补税额",
+    ]);
+    for income in incomes {
+        table.add_row(vec![
+            Cell::new(&income.year),
+            cell(income.dividend),
+            cell(income.realized),
+            cell(income.get_tax()),
+            cell(income.withheld),
+            cell(income.get_payable()),
+        ]);
+    }
+    table.to_string()
+}
+
+/// Shares of a code bought together and sold together, a row of Form 8949.
+#[derive(Debug, Clone)]
+pub struct ClosedLot {
+    code: String,
+    name: String,
+    count: Decimal,
+    acquired: NaiveDate,
+    sold: NaiveDate,
+    proceeds: Decimal,
+    basis: Decimal,
+    // the loss disallowed by wash sale
+    adjustment: Decimal,
+}
+
+impl ClosedLot {
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_count(&self) -> Decimal {
+        self.count
+    }
+
+    pub fn get_acquired(&self) -> &NaiveDate {
+        &self.acquired
+    }
+
+    pub fn get_sold(&self) -> &NaiveDate {
+        &self.sold
+    }
+
+    pub fn get_proceeds(&self) -> Decimal {
+        self.proceeds
+    }
+
+    pub fn get_basis(&self) -> Decimal {
+        self.basis
+    }
+
+    /// The loss disallowed if the lot is a wash sale, with code `W` in Form 8949.
+    pub fn get_adjustment(&self) -> Decimal {
+        self.adjustment
+    }
+
+    pub fn get_gain(&self) -> Decimal {
+        self.proceeds - self.basis + self.adjustment
+    }
+}
+
+/// The lots of overseas codes closed by the sells, matched first in first out.
+///
+/// The shares sold without lots, such as short sales, are not listed.
+pub fn closed_lots(orders: &[DeliveryOrder]) -> Vec<ClosedLot> {
+    let mut orders: Vec<(NaiveDate, &DeliveryOrder)> = orders
+        .iter()
+        .filter(|v| is_overseas(v.get_code()))
+        .filter_map(|v| date::parse_date(v.get_date()).map(|date| (date, v)))
+        .collect();
+    // code => (date, count, cost) of the lots not sold
+    let mut holdings: HashMap<String, VecDeque<(NaiveDate, Decimal, Decimal)>> = HashMap::new();
+    // the lot, and index of the sell
+    let mut lots = vec![];
+
+    // stable, keep the order of rows in same day
+    orders.sort_by_key(|(date, _)| *date);
+    for (idx, (date, order)) in orders.iter().enumerate() {
+        let count = order.get_count().abs();
+        let amount = order.get_amount().unwrap_or_default().abs();
+        let queue = holdings.entry(order.get_code().clone()).or_default();
+
+        match order.get_trade() {
+            Trade::Buy if !count.is_zero() => queue.push_back((*date, count, amount)),
+            Trade::Sell if !count.is_zero() => {
+                let mut left = count;
+
+                while let Some(lot) = queue.front_mut().filter(|_| left > Decimal::ZERO) {
+                    let closed = left.min(lot.1);
+                    let basis = lot.2 * closed / lot.1;
+
+                    lots.push((
+                        ClosedLot {
+                            code: order.get_code().clone(),
+                            name: order.get_name().clone(),
+                            count: closed,
+                            acquired: lot.0,
+                            sold: *date,
+                            proceeds: number::round(amount * closed / count, 2),
+                            basis: number::round(basis, 2),
+                            adjustment: Decimal::ZERO,
+                        },
+                        idx,
+                    ));
+                    lot.1 -= closed;
+                    lot.2 -= basis;
+                    left -= closed;
+                    if lot.1.is_zero() {
+                        queue.pop_front();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    lots.into_iter()
+        .map(|(mut lot, idx)| {
+            // a loss is washed by the buy of the code in the days after the sell
+            let washed = lot.proceeds < lot.basis
+                && orders[idx + 1..].iter().any(|(date, order)| {
+                    order.get_trade() == &Trade::Buy
+                        && order.get_code() == &lot.code
+                        && (*date - lot.sold).num_days() <= WASH_SALE_DAYS
+                });
+
+            if washed {
+                lot.adjustment = lot.basis - lot.proceeds;
+            }
+            lot
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(date: &str, code: &str, trade: Trade, count: i64, amount: i64) -> DeliveryOrder {
+        DeliveryOrder::default()
+            .with_date(date.to_owned())
+            .with_code(code.to_owned())
+            .with_count(Decimal::from(count))
+            .with_amount(Some(Decimal::from(amount)))
+            .with_trade(trade)
+    }
+
+    #[test]
+    fn profits_by_average_cost() {
+        let profits = profits(&[
+            order("20230103", "600000", Trade::Buy, 100, -1000),
+            order("20230104", "600000", Trade::Buy, 100, -2000),
+            order("20230105", "600000", Trade::Sell, 100, 1800),
+            order("20230106", "600000", Trade::Dividend, 0, 50),
+        ]);
+
+        assert_eq!(profits.len(), 1);
+        assert_eq!(profits[0].get_realized(), Decimal::from(300));
+        assert_eq!(profits[0].get_income(), Decimal::from(50));
+        assert_eq!(profits[0].get_count(), 100);
+        assert_eq!(profits[0].get_cost(), Decimal::from(1500));
+        // A-share is not checked for wash sales
+        assert_eq!(profits[0].get_washed(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn closed_lots_first_in_first_out() {
+        let lots = closed_lots(&[
+            order("2023-01-03", "AAPL", Trade::Buy, 10, -1000),
+            order("2023-02-03", "AAPL", Trade::Buy, 10, -2000),
+            order("2023-03-03", "AAPL", Trade::Sell, 15, 1500),
+            order("2023-03-20", "AAPL", Trade::Buy, 5, -500),
+            order("2023-03-03", "600000", Trade::Sell, 100, 1000),
+        ]);
+
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots[0].get_count(), Decimal::from(10));
+        assert_eq!(lots[0].get_basis(), Decimal::from(1000));
+        assert_eq!(lots[0].get_proceeds(), Decimal::from(1000));
+        assert_eq!(lots[1].get_count(), Decimal::from(5));
+        assert_eq!(lots[1].get_basis(), Decimal::from(1000));
+        assert_eq!(lots[1].get_proceeds(), Decimal::from(500));
+        // the loss is washed by the buy in 30 days
+        assert_eq!(lots[1].get_adjustment(), Decimal::from(500));
+        assert_eq!(lots[1].get_gain(), Decimal::ZERO);
+    }
+}
@@ -0,0 +1,96 @@
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::broker::{BrokerParser, FileOrders};
+use crate::error::ParseError;
+use crate::htsc::Context;
+use crate::number;
+use crate::overseas::{self, Record};
+use crate::{DeliveryOrder, Trade};
+
+const DATE_KEYS: [&'static str; 2] = ["Activity Date", "Process Date"];
+
+/// The account activity export of Robinhood, a UTF-8 CSV.
+#[derive(Debug)]
+pub struct Robinhood;
+
+impl BrokerParser for Robinhood {
+    fn get_name(&self) -> &'static str {
+        crate::ROBINHOOD_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.contains("Trans Code") && DATE_KEYS.iter().any(|key| line.contains(key))
+    }
+
+    fn get_encoding(&self) -> &'static Encoding {
+        UTF_8
+    }
+
+    fn read_file<'a>(&'a self, ctx: &'a mut Context, path: &'a str) -> Option<FileOrders<'a>> {
+        Some(Box::pin(overseas::read_orders(ctx, path, to_order, true)))
+    }
+}
+
+/// Convert a row of Robinhood account activity export, the rows are newest first.
+///
+/// Only the trades, bank transfers and cash dividends are kept.
+pub fn to_order(record: &Record) -> Result<Option<DeliveryOrder>, ParseError> {
+    let date = match record.get(&DATE_KEYS).and_then(overseas::parse_date) {
+        Some(date) => date,
+        None => return Ok(None),
+    };
+    let code = record.get(&["Instrument"]).unwrap_or_default();
+    // the description contains the name in the first line and the CUSIP in the second
+    let name = record
+        .get(&["Description"])
+        .and_then(|v| v.lines().next())
+        .unwrap_or(code);
+    let amount = overseas::money_of(record, &["Amount"])?;
+
+    Ok(match record.get(&["Trans Code"]) {
+        Some(trans @ ("Buy" | "Sell")) => {
+            // quantity of shares received from a split is suffixed with `S`
+            let count = match record.get(&["Quantity"]) {
+                Some(value) => {
+                    number::parse_number(value.trim_end_matches(|v: char| v.is_ascii_alphabetic()))
+                        .ok_or_else(|| ParseError::Number {
+                            column: "Quantity".to_owned(),
+                            value: value.to_owned(),
+                        })?
+                }
+                None => return Ok(None),
+            };
+            let price = match overseas::money_of(record, &["Price"])? {
+                Some(price) => price,
+                None => return Ok(None),
+            };
+            let trade = if trans == "Buy" {
+                Trade::Buy
+            } else {
+                Trade::Sell
+            };
+
+            Some(overseas::trade_order(
+                trade, date, code, name, count, price, amount,
+            ))
+        }
+        Some("ACH" | "RTP" | "DCF") => amount.map(|amount| {
+            let trade = if amount.is_sign_negative() {
+                Trade::Out
+            } else {
+                Trade::In
+            };
+
+            overseas::cash_order(trade, date, name, amount)
+        }),
+        Some("CDIV") => amount.map(|amount| {
+            overseas::cash_order(Trade::Dividend, date, name, amount).with_code(code.to_owned())
+        }),
+        // the tax withheld from the dividend
+        Some("DTAX") => amount.map(|amount| {
+            overseas::cash_order(Trade::Custom("红利扣税".to_owned()), date, name, amount)
+                .with_code(code.to_owned())
+        }),
+        _ => None,
+    })
+}
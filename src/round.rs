@@ -0,0 +1,97 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+
+use crate::DeliveryOrder;
+
+/// How the digits beyond the decimal places are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// round half away from zero
+    HalfUp,
+    /// round half to even, aka banker's rounding
+    HalfEven,
+    /// truncate toward zero
+    Down,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::HalfUp
+    }
+}
+
+impl Mode {
+    /// Round `value` to `places`, the trailing zeros are kept.
+    pub fn round(&self, value: Decimal, places: u32) -> Decimal {
+        let mut value = value.round_dp_with_strategy(
+            places,
+            match self {
+                Mode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+                Mode::HalfEven => RoundingStrategy::MidpointNearestEven,
+                Mode::Down => RoundingStrategy::ToZero,
+            },
+        );
+
+        value.rescale(places);
+        value
+    }
+}
+
+/// Decimal places of the numbers in output, keep as it is if not set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Rounding {
+    mode: Mode,
+    price: Option<u32>,
+    amount: Option<u32>,
+    // the fee columns, 佣金, 印花税 etc.
+    fee: Option<u32>,
+}
+
+impl Rounding {
+    pub fn is_empty(&self) -> bool {
+        self.price.is_none() && self.amount.is_none() && self.fee.is_none()
+    }
+
+    pub fn get_price(&self) -> Option<u32> {
+        self.price
+    }
+
+    pub fn get_amount(&self) -> Option<u32> {
+        self.amount
+    }
+
+    pub fn get_fee(&self) -> Option<u32> {
+        self.fee
+    }
+
+    fn round(&self, value: Option<Decimal>, places: Option<u32>) -> Option<Decimal> {
+        Some(self.mode.round(value?, places?))
+    }
+
+    pub fn apply(&self, order: &mut DeliveryOrder) {
+        if let Some(prize) = self.round(order.get_prize(), self.price) {
+            order.set_prize(Some(prize));
+        }
+        if let Some(amount) = self.round(order.get_amount(), self.amount) {
+            order.set_amount(Some(amount));
+        }
+        for (get, set) in [
+            (
+                DeliveryOrder::get_fee as fn(&DeliveryOrder) -> Option<Decimal>,
+                DeliveryOrder::set_fee as fn(&mut DeliveryOrder, Option<Decimal>),
+            ),
+            (DeliveryOrder::get_commission, DeliveryOrder::set_commission),
+            (DeliveryOrder::get_stamp_tax, DeliveryOrder::set_stamp_tax),
+            (
+                DeliveryOrder::get_transfer_fee,
+                DeliveryOrder::set_transfer_fee,
+            ),
+        ] {
+            if let Some(fee) = self.round(get(order), self.fee) {
+                set(order, Some(fee));
+            }
+        }
+    }
+}
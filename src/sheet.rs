@@ -0,0 +1,43 @@
+use calamine::{open_workbook_auto, DataType, Reader};
+
+const SHEET_EXTENSIONS: [&'static str; 2] = [".xlsx", ".xls"];
+
+/// Return true if the `path` is a spreadsheet could be read by calamine.
+pub fn is_sheet_path(path: &str) -> bool {
+    let path = path.to_lowercase();
+
+    SHEET_EXTENSIONS.iter().any(|v| path.ends_with(v))
+}
+
+/// Text of the cell as shown in the exported statement.
+pub fn cell_text(value: &DataType) -> String {
+    match value {
+        // the dates are formatted cells of number
+        DataType::DateTime(_) => value
+            .as_date()
+            .map(|v| v.format("%Y%m%d").to_string())
+            .unwrap_or_else(|| value.to_string()),
+        DataType::Empty => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Read the rows of all sheets as tab separated lines, with the line number in sheet.
+///
+/// So a statement saved as spreadsheet is parsed same as the text one.
+pub fn read_lines(path: &str) -> Result<Vec<(usize, String)>, calamine::Error> {
+    let mut workbook = open_workbook_auto(path)?;
+    let mut lines = vec![];
+
+    for name in workbook.sheet_names().to_owned() {
+        if let Some(range) = workbook.worksheet_range(&name) {
+            for (idx, row) in range?.rows().enumerate() {
+                let line = row.iter().map(cell_text).collect::<Vec<_>>().join("\t");
+
+                lines.push((idx + 1, line));
+            }
+        }
+    }
+
+    Ok(lines)
+}
@@ -0,0 +1,110 @@
+use comfy_table::{Cell, CellAlignment, Table};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use crate::number;
+use crate::{DeliveryOrder, Trade};
+
+#[derive(Debug, Default)]
+pub struct Item {
+    name: String,
+    rows: usize,
+    buy: i64,
+    sell: i64,
+    owned: Option<Decimal>,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    rows: usize,
+    // rows dropped by `--dedup`
+    duplicates: usize,
+    // (account, code) => item
+    items: BTreeMap<(String, String), Item>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, order: &DeliveryOrder) {
+        self.rows += 1;
+        if order.get_code().is_empty() {
+            return;
+        }
+
+        let item = self
+            .items
+            .entry((order.get_account().clone(), order.get_code().clone()))
+            .or_default();
+        let count = order.get_count().trunc().to_i64().unwrap_or(0);
+
+        item.name = order.get_name().clone();
+        item.rows += 1;
+        item.owned = order.get_owned();
+        match order.get_trade() {
+            Trade::Buy => item.buy += count,
+            Trade::Sell => item.sell += count.abs(),
+            _ => {}
+        }
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn set_duplicates(&mut self, duplicates: usize) {
+        self.duplicates = duplicates;
+    }
+
+    pub fn print(&self) {
+        println!("{}", self.render());
+    }
+
+    /// Render the summary as a text table, followed by the total rows.
+    ///
+    /// The account column is shown only if the orders have account.
+    pub fn render(&self) -> String {
+        let mut table = Table::new();
+        let with_account = self.items.keys().any(|(account, _)| !account.is_empty());
+        let mut header = vec![
+            "证券代码",
+            "证券名称",
+            "记录数",
+            "买入数量",
+            "卖出数量",
+            "证券余额",
+        ];
+
+        if with_account {
+            header.insert(0, crate::ACCOUNT_TITLE);
+        }
+        table.set_header(header);
+        for ((account, code), item) in self.items.iter() {
+            let mut row = vec![];
+
+            if with_account {
+                row.push(Cell::new(account));
+            }
+            row.extend([
+                Cell::new(code),
+                Cell::new(&item.name),
+                Cell::new(item.rows).set_alignment(CellAlignment::Right),
+                Cell::new(item.buy).set_alignment(CellAlignment::Right),
+                Cell::new(item.sell).set_alignment(CellAlignment::Right),
+                Cell::new(number::format_number(item.owned)).set_alignment(CellAlignment::Right),
+            ]);
+            table.add_row(row);
+        }
+        if self.duplicates > 0 {
+            format!(
+                "{}\ntotal rows = {}, dropped duplicates = {}",
+                table, self.rows, self.duplicates
+            )
+        } else {
+            format!("{}\ntotal rows = {}", table, self.rows)
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use calamine::{open_workbook, DataType, Range, Reader as _, Xlsx};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::error::Error;
+use crate::tzzb;
+
+const WORKBOOK_PART: &'static str = "xl/workbook.xml";
+const RELS_PART: &'static str = "xl/_rels/workbook.xml.rels";
+const STYLES_PART: &'static str = "xl/styles.xml";
+// the last column index of an excel sheet
+const MAX_COLUMN: u16 = 16_383;
+
+/// Number format, font, fill and alignment of a cell in template.
+#[derive(Debug, Default, Clone)]
+pub struct CellStyle {
+    // the built-in number format
+    num_format_index: u8,
+    // the custom number format
+    num_format: Option<String>,
+    bold: bool,
+    italic: bool,
+    font_name: Option<String>,
+    font_size: Option<f64>,
+    // RGB of the font and the solid fill
+    font_color: Option<u32>,
+    fill: Option<u32>,
+    align: Option<String>,
+    valign: Option<String>,
+    wrap: bool,
+}
+
+impl CellStyle {
+    pub fn get_num_format_index(&self) -> u8 {
+        self.num_format_index
+    }
+
+    pub fn get_num_format(&self) -> Option<&str> {
+        self.num_format.as_deref()
+    }
+
+    pub fn get_bold(&self) -> bool {
+        self.bold
+    }
+
+    pub fn get_italic(&self) -> bool {
+        self.italic
+    }
+
+    pub fn get_font_name(&self) -> Option<&str> {
+        self.font_name.as_deref()
+    }
+
+    pub fn get_font_size(&self) -> Option<f64> {
+        self.font_size
+    }
+
+    pub fn get_font_color(&self) -> Option<u32> {
+        self.font_color
+    }
+
+    pub fn get_fill(&self) -> Option<u32> {
+        self.fill
+    }
+
+    /// The horizontal alignment of OOXML, e.g. `center`.
+    pub fn get_align(&self) -> Option<&str> {
+        self.align.as_deref()
+    }
+
+    /// The vertical alignment of OOXML, e.g. `top`.
+    pub fn get_valign(&self) -> Option<&str> {
+        self.valign.as_deref()
+    }
+
+    pub fn get_wrap(&self) -> bool {
+        self.wrap
+    }
+}
+
+/// Column widths, frozen panes and cell styles of a sheet in template.
+#[derive(Debug, Default, Clone)]
+pub struct SheetStyle {
+    // (first column, last column, width)
+    widths: Vec<(u16, u16, f64)>,
+    // (rows, columns) frozen
+    pane: Option<(u32, u16)>,
+    // (row, column) => index of style in [`Template::get_styles`]
+    cells: HashMap<(u32, u16), usize>,
+}
+
+impl SheetStyle {
+    pub fn get_widths(&self) -> &Vec<(u16, u16, f64)> {
+        &self.widths
+    }
+
+    pub fn get_pane(&self) -> Option<(u32, u16)> {
+        self.pane
+    }
+
+    pub fn get_cells(&self) -> &HashMap<(u32, u16), usize> {
+        &self.cells
+    }
+}
+
+/// The workbook of `--template`, the output copies its sheets and formatting.
+#[derive(Debug, Default)]
+pub struct Template {
+    styles: Vec<CellStyle>,
+    // the sheets are not the orders, with their values
+    sheets: Vec<(String, Range<DataType>, SheetStyle)>,
+    // style of the first order sheet, the rows of it are not copied
+    orders: Option<SheetStyle>,
+    // sheet name => (row, column) => formula, without the leading `=`
+    formulas: HashMap<String, HashMap<(u32, u16), String>>,
+}
+
+impl Template {
+    pub fn get_styles(&self) -> &Vec<CellStyle> {
+        &self.styles
+    }
+
+    pub fn get_sheets(&self) -> &Vec<(String, Range<DataType>, SheetStyle)> {
+        &self.sheets
+    }
+
+    pub fn get_orders(&self) -> Option<&SheetStyle> {
+        self.orders.as_ref()
+    }
+
+    /// The formulas of sheet `name`, the values of them are the cached results.
+    pub fn get_formulas(&self, name: &str) -> Option<&HashMap<(u32, u16), String>> {
+        self.formulas.get(name)
+    }
+}
+
+/// Read the sheets and formatting of template `path`.
+///
+/// The values and formulas are read by calamine, the styles which calamine can't
+/// read are parsed from the parts of xlsx package.
+pub fn read(path: &str) -> Result<Template, Error> {
+    let mut values: HashMap<String, Range<DataType>> =
+        tzzb::read_other_sheets(path)?.into_iter().collect();
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut template = Template::default();
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(calamine::Error::from)?;
+
+    for name in values.keys() {
+        if let Some(range) = workbook.worksheet_formula(name) {
+            let range = range.map_err(calamine::Error::from)?;
+            let (row_start, col_start) = range.start().unwrap_or((0, 0));
+            let formulas: HashMap<(u32, u16), String> = range
+                .used_cells()
+                .filter(|(_, _, formula)| !formula.is_empty())
+                .map(|(row, col, formula)| {
+                    (
+                        (row_start + row as u32, (col_start + col as u32) as u16),
+                        formula.clone(),
+                    )
+                })
+                .collect();
+
+            if !formulas.is_empty() {
+                template.formulas.insert(name.clone(), formulas);
+            }
+        }
+    }
+
+    if let Some(xml) = read_part(&mut archive, STYLES_PART)? {
+        template.styles = read_styles(&xml)?;
+    }
+    for (name, part) in read_sheet_parts(&mut archive)? {
+        let style = match read_part(&mut archive, &part)? {
+            Some(xml) => read_sheet_style(&xml)?,
+            None => SheetStyle::default(),
+        };
+
+        match values.remove(&name) {
+            Some(range) => template.sheets.push((name, range, style)),
+            None if template.orders.is_none() => template.orders = Some(style),
+            None => {}
+        }
+    }
+
+    Ok(template)
+}
+
+/// Read the part `name` of package, `None` if it is not exist.
+fn read_part(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<String>, Error> {
+    match archive.by_name(name) {
+        Ok(mut entry) => {
+            let mut content = String::new();
+
+            entry.read_to_string(&mut content)?;
+            Ok(Some(content))
+        }
+        Err(ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Attributes of `element` keyed by the local name, e.g. `id` of `r:id`.
+fn attributes(element: &BytesStart) -> Result<HashMap<String, String>, quick_xml::Error> {
+    let mut ret = HashMap::new();
+
+    for attr in element.attributes() {
+        let attr = attr?;
+
+        ret.insert(
+            String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+            attr.unescape_value()?.into_owned(),
+        );
+    }
+    Ok(ret)
+}
+
+/// The name and part path of sheets, in the order of workbook.
+fn read_sheet_parts(archive: &mut ZipArchive<File>) -> Result<Vec<(String, String)>, Error> {
+    let mut targets = HashMap::new();
+    let mut sheets = vec![];
+
+    if let Some(xml) = read_part(archive, RELS_PART)? {
+        let mut reader = Reader::from_str(&xml);
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    let attrs = attributes(&e)?;
+
+                    if let (Some(id), Some(target)) = (attrs.get("Id"), attrs.get("Target")) {
+                        // the target is relative to xl/ if not absolute
+                        let part = match target.strip_prefix('/') {
+                            Some(target) => target.to_owned(),
+                            None => format!("xl/{}", target),
+                        };
+
+                        targets.insert(id.clone(), part);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+    }
+    if let Some(xml) = read_part(archive, WORKBOOK_PART)? {
+        let mut reader = Reader::from_str(&xml);
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sheet" => {
+                    let attrs = attributes(&e)?;
+
+                    if let (Some(name), Some(part)) = (
+                        attrs.get("name"),
+                        attrs.get("id").and_then(|v| targets.get(v)),
+                    ) {
+                        sheets.push((name.clone(), part.clone()));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(sheets)
+}
+
+/// The RGB of `rgb` attribute in ARGB hex, the theme and indexed colors are not supported.
+fn parse_color(attrs: &HashMap<String, String>) -> Option<u32> {
+    let rgb = attrs.get("rgb")?;
+
+    u32::from_str_radix(&rgb[rgb.len().saturating_sub(6)..], 16).ok()
+}
+
+/// The cell styles of `cellXfs`, combined with the fonts, fills and number formats.
+fn read_styles(xml: &str) -> Result<Vec<CellStyle>, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut num_formats: HashMap<u32, String> = HashMap::new();
+    // the font part of cell styles
+    let mut fonts: Vec<CellStyle> = vec![];
+    let mut fills: Vec<Option<u32>> = vec![];
+    let mut solid = false;
+    let mut styles = vec![];
+    // the collection element current in, e.g. fonts
+    let mut section = vec![];
+
+    loop {
+        let (element, empty) = match reader.read_event()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                if section.last().map(|v: &Vec<u8>| v.as_slice()) == Some(e.local_name().as_ref()) {
+                    section.pop();
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = element.local_name().as_ref().to_vec();
+        let attrs = attributes(&element)?;
+
+        match (section.last().map(|v| v.as_slice()), name.as_slice()) {
+            (_, b"numFmts" | b"fonts" | b"fills" | b"cellXfs" | b"cellStyleXfs" | b"borders") => {
+                if !empty {
+                    section.push(name.clone());
+                }
+            }
+            (Some(b"numFmts"), b"numFmt") => {
+                if let (Some(id), Some(code)) = (
+                    attrs.get("numFmtId").and_then(|v| v.parse().ok()),
+                    attrs.get("formatCode"),
+                ) {
+                    num_formats.insert(id, code.clone());
+                }
+            }
+            (Some(b"fonts"), b"font") => fonts.push(CellStyle::default()),
+            (Some(b"fonts"), _) => {
+                if let Some(font) = fonts.last_mut() {
+                    let value = attrs.get("val");
+
+                    match name.as_slice() {
+                        b"b" => font.bold = value.map(|v| v != "0").unwrap_or(true),
+                        b"i" => font.italic = value.map(|v| v != "0").unwrap_or(true),
+                        b"sz" => font.font_size = value.and_then(|v| v.parse().ok()),
+                        b"name" => font.font_name = value.cloned(),
+                        b"color" => font.font_color = parse_color(&attrs),
+                        _ => {}
+                    }
+                }
+            }
+            (Some(b"fills"), b"fill") => fills.push(None),
+            (Some(b"fills"), b"patternFill") => {
+                solid = attrs.get("patternType").map(|v| v.as_str()) == Some("solid");
+            }
+            (Some(b"fills"), b"fgColor") if solid => {
+                if let Some(fill) = fills.last_mut() {
+                    *fill = parse_color(&attrs);
+                }
+            }
+            (Some(b"cellXfs"), b"xf") => {
+                let index = |key: &str| {
+                    attrs
+                        .get(key)
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0)
+                };
+                let mut style = fonts.get(index("fontId")).cloned().unwrap_or_default();
+                let num_format = index("numFmtId") as u32;
+
+                style.fill = fills.get(index("fillId")).cloned().flatten();
+                match num_formats.get(&num_format) {
+                    Some(code) => style.num_format = Some(code.clone()),
+                    None => style.num_format_index = num_format.min(u8::MAX as u32) as u8,
+                }
+                styles.push(style);
+            }
+            (Some(b"cellXfs"), b"alignment") => {
+                if let Some(style) = styles.last_mut() {
+                    style.align = attrs.get("horizontal").cloned();
+                    style.valign = attrs.get("vertical").cloned();
+                    style.wrap = attrs.get("wrapText").map(|v| v != "0").unwrap_or(false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(styles)
+}
+
+/// Parse the cell reference like `B12` into (row, column) from zero.
+fn parse_cell(reference: &str) -> Option<(u32, u16)> {
+    let split = reference.find(|v: char| v.is_ascii_digit())?;
+    let (column, row) = reference.split_at(split);
+    let column = column
+        .chars()
+        .filter(|v| v.is_ascii_alphabetic())
+        .try_fold(0u32, |acc, v| {
+            Some(acc * 26 + (v.to_ascii_uppercase() as u32 - 'A' as u32 + 1))
+        })?;
+
+    Some((
+        row.parse::<u32>().ok()?.checked_sub(1)?,
+        column.checked_sub(1)? as u16,
+    ))
+}
+
+/// The column widths, frozen panes and styled cells of a worksheet part.
+fn read_sheet_style(xml: &str) -> Result<SheetStyle, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut style = SheetStyle::default();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => {
+                let attrs = match e.local_name().as_ref() {
+                    b"col" | b"pane" | b"c" => attributes(&e)?,
+                    _ => continue,
+                };
+                let number = |key: &str| attrs.get(key).and_then(|v| v.parse::<f64>().ok());
+
+                match e.local_name().as_ref() {
+                    b"col" => {
+                        if let (Some(min), Some(max), Some(width)) =
+                            (number("min"), number("max"), number("width"))
+                        {
+                            style.widths.push((
+                                (min as u16).saturating_sub(1).min(MAX_COLUMN),
+                                (max as u16).saturating_sub(1).min(MAX_COLUMN),
+                                width,
+                            ));
+                        }
+                    }
+                    b"pane" => {
+                        if attrs.get("state").map(|v| v.starts_with("frozen")) == Some(true) {
+                            style.pane = Some((
+                                number("ySplit").unwrap_or(0.0) as u32,
+                                number("xSplit").unwrap_or(0.0) as u16,
+                            ));
+                        }
+                    }
+                    _ => {
+                        let index = number("s").unwrap_or(0.0) as usize;
+
+                        // the default style needs not to be copied
+                        if index > 0 {
+                            if let Some(cell) = attrs.get("r").and_then(|v| parse_cell(v)) {
+                                style.cells.insert(cell, index);
+                            }
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(style)
+}
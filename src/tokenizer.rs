@@ -0,0 +1,215 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialect {
+    delimiter: char,
+    quote: char,
+    // display widths of fixed-width columns, the last one take rest of the line
+    widths: Option<Vec<usize>>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            delimiter: '\t',
+            quote: '"',
+            widths: None,
+        }
+    }
+}
+
+const DELIMITERS: [char; 3] = ['\t', ',', ';'];
+
+impl Dialect {
+    pub fn new(delimiter: char, quote: char) -> Self {
+        Self {
+            delimiter,
+            quote,
+            widths: None,
+        }
+    }
+
+    pub fn fixed_width(widths: Vec<usize>) -> Self {
+        Self {
+            widths: Some(widths),
+            ..Self::default()
+        }
+    }
+
+    /// Guess the delimiter from the header line, the one appear most outside quotes wins.
+    pub fn detect(header: &str) -> Self {
+        let mut dialect = Self::default();
+        let mut counts = [0usize; DELIMITERS.len()];
+        let mut quoted = false;
+
+        for ch in header.chars() {
+            if ch == dialect.quote {
+                quoted = !quoted;
+            } else if !quoted {
+                if let Some(idx) = DELIMITERS.iter().position(|v| *v == ch) {
+                    counts[idx] += 1;
+                }
+            }
+        }
+        let max = counts.iter().max().cloned().unwrap_or(0);
+
+        // prefer tab when tie, it is the original HTSC format
+        if let Some(idx) = counts.iter().position(|v| max > 0 && *v == max) {
+            dialect.delimiter = DELIMITERS[idx];
+        } else if let Some(widths) = Self::infer_widths(header) {
+            dialect.widths = Some(widths);
+        }
+        dialect
+    }
+
+    /// Infer the column widths from a header which columns are aligned by spaces.
+    pub fn infer_widths(header: &str) -> Option<Vec<usize>> {
+        let mut starts = vec![];
+        let mut width = 0;
+        let mut last_space = true;
+
+        for ch in header.chars() {
+            let space = ch.is_whitespace();
+
+            if last_space && !space {
+                starts.push(width);
+            }
+            last_space = space;
+            width += display_width(ch);
+        }
+        if starts.len() > 1 {
+            let mut widths: Vec<usize> = starts.windows(2).map(|v| v[1] - v[0]).collect();
+
+            // the first column may be indented
+            widths[0] += starts[0];
+            widths.push(usize::MAX);
+            Some(widths)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_widths(&self) -> Option<&Vec<usize>> {
+        self.widths.as_ref()
+    }
+
+    pub fn is_fixed_width(&self) -> bool {
+        self.widths.is_some()
+    }
+
+    pub fn get_delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    pub fn get_quote(&self) -> char {
+        self.quote
+    }
+
+    /// Split the line into fields.
+    ///
+    /// A field starting with the quote character may contain the delimiter,
+    /// and a doubled quote character inside it stands for a literal quote.
+    pub fn split(&self, line: &str) -> Vec<String> {
+        if let Some(widths) = &self.widths {
+            return Self::split_fixed_width(widths, line);
+        }
+
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if quoted {
+                if ch == self.quote {
+                    if chars.peek() == Some(&self.quote) {
+                        field.push(self.quote);
+                        chars.next();
+                    } else {
+                        quoted = false;
+                    }
+                } else {
+                    field.push(ch);
+                }
+            } else if ch == self.quote && field.trim().is_empty() {
+                field.clear();
+                quoted = true;
+            } else if ch == self.delimiter {
+                fields.push(std::mem::take(&mut field));
+            } else {
+                field.push(ch);
+            }
+        }
+        fields.push(field);
+        fields
+    }
+
+    fn split_fixed_width(widths: &Vec<usize>, line: &str) -> Vec<String> {
+        let mut fields = vec![];
+        let mut chars = line.chars().peekable();
+
+        for width in widths {
+            let mut field = String::new();
+            let mut used = 0;
+
+            while let Some(&ch) = chars.peek() {
+                if used >= *width {
+                    break;
+                }
+                used += display_width(ch);
+                field.push(ch);
+                chars.next();
+            }
+            fields.push(field);
+            if chars.peek().is_none() {
+                break;
+            }
+        }
+        fields
+    }
+}
+
+/// Terminal exports align the columns in GBK bytes, a CJK character take two columns.
+fn display_width(ch: char) -> usize {
+    if ch.is_ascii() {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_by_delimiter() {
+        let dialect = Dialect::detect("发生日期,证券代码,证券名称");
+
+        assert_eq!(dialect.get_delimiter(), ',');
+        assert_eq!(
+            dialect.split("20230103,600000,浦发银行"),
+            vec!["20230103", "600000", "浦发银行"]
+        );
+        assert_eq!(dialect.split("20230103,,"), vec!["20230103", "", ""]);
+    }
+
+    #[test]
+    fn split_quoted() {
+        let dialect = Dialect::new(',', '"');
+
+        assert_eq!(
+            dialect.split(r#"20230103,"1,000.00","say ""hi""""#),
+            vec!["20230103", "1,000.00", r#"say "hi""#]
+        );
+    }
+
+    #[test]
+    fn split_fixed_width() {
+        let dialect = Dialect::detect("日期      代码    名称");
+
+        assert!(dialect.is_fixed_width());
+        assert_eq!(
+            dialect.split("20230103  600000  浦发银行"),
+            vec!["20230103  ", "600000  ", "浦发银行"]
+        );
+    }
+}
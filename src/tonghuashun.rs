@@ -0,0 +1,37 @@
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::broker::BrokerParser;
+use crate::mapping::Sign;
+
+/// The trade history exported by 同花顺, a UTF-8 CSV.
+///
+/// The 成交金额 is not signed, so 发生金额 is derived from quantity, price and fees.
+#[derive(Debug)]
+pub struct Tonghuashun;
+
+impl BrokerParser for Tonghuashun {
+    fn get_name(&self) -> &'static str {
+        crate::TONGHUASHUN_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.contains("操作") && line.contains("成交均价")
+    }
+
+    fn get_encoding(&self) -> &'static Encoding {
+        UTF_8
+    }
+
+    fn get_sign(&self) -> Sign {
+        Sign::Unsigned
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        match title {
+            "成交日期" => "发生日期",
+            "操作" => "业务名称",
+            title => title,
+        }
+        .to_owned()
+    }
+}
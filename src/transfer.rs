@@ -0,0 +1,53 @@
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::{DeliveryOrder, Trade};
+
+const TRANSFER_IN: &'static str = "担保品划入";
+const TRANSFER_OUT: &'static str = "担保品划出";
+
+/// Pair the 担保品划出 and 担保品划入 legs of same day, code and quantity between
+/// two accounts, and record the account of the other leg as counterpart of each.
+///
+/// The legs without a pair are kept as they are, e.g. only one statement is given.
+pub fn pair_transfers(orders: &mut [DeliveryOrder]) {
+    let transfer_in = Trade::Custom(TRANSFER_IN.to_owned());
+    let transfer_out = Trade::Custom(TRANSFER_OUT.to_owned());
+    // (date, code, quantity) => index of the 划出 legs not paired yet
+    let mut outs: HashMap<(String, String, Decimal), Vec<usize>> = HashMap::new();
+
+    for (idx, order) in orders.iter().enumerate() {
+        if order.get_trade() == &transfer_out {
+            outs.entry(key_of(order)).or_default().push(idx);
+        }
+    }
+    for idx in 0..orders.len() {
+        if orders[idx].get_trade() != &transfer_in {
+            continue;
+        }
+        let legs = match outs.get_mut(&key_of(&orders[idx])) {
+            Some(legs) => legs,
+            None => continue,
+        };
+
+        if let Some(pos) = legs
+            .iter()
+            .position(|v| orders[*v].get_account() != orders[idx].get_account())
+        {
+            let out = legs.remove(pos);
+            let from = orders[out].get_account().clone();
+            let to = orders[idx].get_account().clone();
+
+            orders[idx].set_counterpart(from);
+            orders[out].set_counterpart(to);
+        }
+    }
+}
+
+fn key_of(order: &DeliveryOrder) -> (String, String, Decimal) {
+    (
+        order.get_date().clone(),
+        order.get_code().clone(),
+        order.get_count().abs().normalize(),
+    )
+}
@@ -0,0 +1,161 @@
+use calamine::{open_workbook, DataType, Range, Reader, Xlsx};
+use rust_decimal::Decimal;
+
+use crate::broker::{BrokerParser, FileOrders};
+use crate::error::Error;
+use crate::htsc::{self, Context};
+use crate::number;
+use crate::{DeliveryOrder, Trade};
+
+/// The workbook generated by this tool, the rows are read back with the balances kept as it is.
+#[derive(Debug)]
+pub struct Tzzb;
+
+impl BrokerParser for Tzzb {
+    fn get_name(&self) -> &'static str {
+        crate::TZZB_TYPE
+    }
+
+    /// The rows of sheet are joined by tab, see [`crate::sheet::read_lines`].
+    fn detect(&self, line: &str) -> bool {
+        let title = htsc::Context::gen_title();
+
+        line.split('\t')
+            .map(|v| v.trim())
+            .take(title.len())
+            .eq(title.iter().map(|v| v.as_str()))
+    }
+
+    fn read_file<'a>(&'a self, _ctx: &'a mut Context, path: &'a str) -> Option<FileOrders<'a>> {
+        Some(Box::pin(
+            async move { read_orders(path).map_err(Error::from) },
+        ))
+    }
+}
+
+/// Map the 交易类别 written in output back to [`Trade`].
+pub fn trade_of_kind(kind: &str) -> Trade {
+    match kind {
+        "买入" => Trade::Buy,
+        "卖出" => Trade::Sell,
+        "银证转入" => Trade::In,
+        "银证转出" => Trade::Out,
+        "股息入账" => Trade::Dividend,
+        "利息归本" => Trade::Interest,
+        "费用扣收" => Trade::Fee,
+        "红股入账" => Trade::BonusShares,
+        "" => Trade::Ignore,
+        _ => Trade::Custom(kind.to_owned()),
+    }
+}
+
+/// Return true if the `header` is same as `title`, the provenance columns may follow.
+fn is_order_header(header: &[DataType], title: &[String]) -> bool {
+    header.len() >= title.len()
+        && header
+            .iter()
+            .take(title.len())
+            .map(|v| v.to_string())
+            .eq(title.iter().cloned())
+}
+
+/// Read the values of sheets which are not the orders, in the order of workbook.
+pub fn read_other_sheets(path: &str) -> Result<Vec<(String, Range<DataType>)>, calamine::Error> {
+    let mut workbook: Xlsx<_> = open_workbook(path)?;
+    let title = htsc::Context::gen_title();
+    let mut sheets = vec![];
+
+    for name in workbook.sheet_names().to_owned() {
+        if let Some(range) = workbook.worksheet_range(&name) {
+            let range = range?;
+
+            match range.rows().next() {
+                Some(header) if is_order_header(header, &title) => {}
+                _ => sheets.push((name, range)),
+            }
+        }
+    }
+
+    Ok(sheets)
+}
+
+/// Read the last 资金余额 of workbook generated by this tool, `None` if it has no cash sheet.
+pub fn read_cash_balance(path: &str) -> Result<Option<Decimal>, calamine::Error> {
+    let mut workbook: Xlsx<_> = open_workbook(path)?;
+
+    for name in workbook.sheet_names().to_owned() {
+        if let Some(range) = workbook.worksheet_range(&name) {
+            let range = range?;
+            let mut rows = range.rows();
+            let column = match rows.next().and_then(|header| {
+                header
+                    .iter()
+                    .position(|v| v.to_string() == crate::BALANCE_TITLE)
+            }) {
+                Some(column) => column,
+                None => continue,
+            };
+
+            return Ok(rows
+                .filter_map(|row| row.get(column))
+                .filter_map(|v| number::parse_number(&v.to_string()))
+                .last());
+        }
+    }
+
+    Ok(None)
+}
+
+/// Read the rows of workbook generated by this tool.
+///
+/// Only the sheets have same title as [`htsc::Context::gen_title`] are read, extra columns are ignored.
+pub fn read_orders(path: &str) -> Result<Vec<DeliveryOrder>, calamine::Error> {
+    let mut workbook: Xlsx<_> = open_workbook(path)?;
+    let title = htsc::Context::gen_title();
+    let mut orders = vec![];
+
+    for name in workbook.sheet_names().to_owned() {
+        if let Some(range) = workbook.worksheet_range(&name) {
+            let range = range?;
+            let mut rows = range.rows();
+
+            // the optional 资金账号 column follows the title
+            let account = match rows.next() {
+                Some(header) if is_order_header(header, &title) => header
+                    .iter()
+                    .position(|v| v.to_string() == crate::ACCOUNT_TITLE),
+                _ => continue,
+            };
+            for (idx, row) in rows.enumerate() {
+                let cell = |idx: usize| row.get(idx).map(|v| v.to_string()).unwrap_or_default();
+                // the dates are written as excel dates
+                let date = match row.first() {
+                    Some(DataType::DateTime(_)) => row[0]
+                        .as_date()
+                        .map(|v| v.format("%Y%m%d").to_string())
+                        .unwrap_or_default(),
+                    _ => cell(0),
+                };
+
+                orders.push(
+                    DeliveryOrder::default()
+                        .with_date(date)
+                        .with_code(cell(1))
+                        .with_name(cell(2))
+                        .with_kind(cell(3))
+                        .with_count(number::parse_number(&cell(4)).unwrap_or_default())
+                        .with_prize(number::parse_number(&cell(5)))
+                        .with_amount(number::parse_number(&cell(6)))
+                        .with_owned(number::parse_number(&cell(7)))
+                        .with_account(account.map(cell).unwrap_or_default())
+                        .with_trade(trade_of_kind(&cell(3)))
+                        .with_source(format!("{}[{}]", path, name))
+                        // header is the first line
+                        .with_line(idx + 2),
+                );
+            }
+        }
+    }
+
+    Ok(orders)
+}
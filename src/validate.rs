@@ -0,0 +1,98 @@
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+
+use crate::mapping::TradeMap;
+use crate::DeliveryOrder;
+
+/// A row whose 证券余额 is not the balance of previous row plus its quantity.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    account: String,
+    code: String,
+    name: String,
+    date: String,
+    source: String,
+    line: usize,
+    expected: i64,
+    computed: i64,
+}
+
+impl Mismatch {
+    pub fn get_account(&self) -> &String {
+        &self.account
+    }
+
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_date(&self) -> &String {
+        &self.date
+    }
+
+    pub fn get_source(&self) -> &String {
+        &self.source
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    /// The 证券余额 of the row.
+    pub fn get_expected(&self) -> i64 {
+        self.expected
+    }
+
+    /// The balance computed from previous rows.
+    pub fn get_computed(&self) -> i64 {
+        self.computed
+    }
+}
+
+/// Check the 证券余额 of `orders` row by row.
+///
+/// The first row of each code in an account is taken as the opening balance, and the check
+/// continues from the 证券余额 after a mismatch, so every break is reported once.
+pub fn check_balances(orders: &[DeliveryOrder], trades: &TradeMap) -> Vec<Mismatch> {
+    // (account, code) => balance
+    let mut balances: HashMap<(&String, &String), i64> = HashMap::new();
+    let mut mismatches = vec![];
+
+    for order in orders.iter().filter(|v| !v.get_code().is_empty()) {
+        let expected = match order.get_owned().and_then(|v| v.trunc().to_i64()) {
+            Some(owned) => owned,
+            None => continue,
+        };
+        let count = order.get_count().trunc().to_i64().unwrap_or(0);
+
+        let key = (order.get_account(), order.get_code());
+
+        if let Some(balance) = balances.get(&key) {
+            let computed = balance
+                + trades
+                    .category(order.get_trade())
+                    .get_holdings()
+                    .apply(count);
+
+            if computed != expected {
+                mismatches.push(Mismatch {
+                    account: order.get_account().clone(),
+                    code: order.get_code().clone(),
+                    name: order.get_name().clone(),
+                    date: order.get_date().clone(),
+                    source: order.get_source().clone(),
+                    line: order.get_line(),
+                    expected,
+                    computed,
+                });
+            }
+        }
+        balances.insert(key, expected);
+    }
+
+    mismatches
+}
@@ -0,0 +1,140 @@
+pub mod beancount;
+pub mod csv;
+pub mod json;
+pub mod parquet;
+pub mod qif;
+pub mod sqlite;
+pub mod xlsx;
+
+use crate::error::Error;
+use crate::feather;
+use crate::layout::Layout;
+use crate::mapping::TradeMap;
+use crate::DeliveryOrder;
+
+const XLSX_TYPE: &'static str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+const ARROW_TYPE: &'static str = "application/vnd.apache.arrow.file";
+const CSV_TYPE: &'static str = "text/csv";
+const JSON_TYPE: &'static str = "application/x-ndjson";
+const SQLITE_TYPE: &'static str = "application/vnd.sqlite3";
+const PARQUET_TYPE: &'static str = "application/vnd.apache.parquet";
+const BEANCOUNT_TYPE: &'static str = "text/plain";
+const QIF_TYPE: &'static str = "application/qif";
+
+/// Format of the output, given by `--format` or inferred from the extension of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Xlsx,
+    Arrow,
+    Csv,
+    // NDJSON, one order per line
+    Json,
+    // rows are appended to the table across runs
+    Sqlite,
+    Parquet,
+    // plain-text accounting
+    Beancount,
+    // investment transactions of personal finance apps
+    Qif,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "xlsx" => Some(Self::Xlsx),
+            "arrow" | "feather" => Some(Self::Arrow),
+            "csv" => Some(Self::Csv),
+            "json" | "ndjson" => Some(Self::Json),
+            "sqlite" => Some(Self::Sqlite),
+            "parquet" => Some(Self::Parquet),
+            "beancount" => Some(Self::Beancount),
+            "qif" => Some(Self::Qif),
+            _ => None,
+        }
+    }
+
+    /// Infer the format from extension of `path`, default is xlsx.
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_lowercase();
+
+        if feather::is_arrow_path(path) {
+            Self::Arrow
+        } else if lower.ends_with(".parquet") {
+            Self::Parquet
+        } else if lower.ends_with(".beancount") || lower.ends_with(".bean") {
+            Self::Beancount
+        } else if lower.ends_with(".qif") {
+            Self::Qif
+        } else if lower.ends_with(".csv") {
+            Self::Csv
+        } else if path == json::STDOUT_PATH
+            || [".json", ".ndjson", ".jsonl"]
+                .iter()
+                .any(|v| lower.ends_with(v))
+        {
+            Self::Json
+        } else if [".db", ".sqlite", ".sqlite3"]
+            .iter()
+            .any(|v| lower.ends_with(v))
+        {
+            Self::Sqlite
+        } else {
+            Self::Xlsx
+        }
+    }
+
+    /// The output is a workbook, which can be appended or has the extra sheets.
+    pub fn is_workbook(&self) -> bool {
+        self == &Self::Xlsx
+    }
+
+    /// MIME type of the output, for the attachment of email.
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            Self::Xlsx => XLSX_TYPE,
+            Self::Arrow => ARROW_TYPE,
+            Self::Csv => CSV_TYPE,
+            Self::Json => JSON_TYPE,
+            Self::Sqlite => SQLITE_TYPE,
+            Self::Parquet => PARQUET_TYPE,
+            Self::Beancount => BEANCOUNT_TYPE,
+            Self::Qif => QIF_TYPE,
+        }
+    }
+}
+
+/// Write the orders into `path` in `format`.
+///
+/// The workbook has no template sheets and audit log, and the beancount
+/// output uses the default account names.
+pub fn write_orders(
+    path: &str,
+    format: Format,
+    orders: &[DeliveryOrder],
+    layout: &Layout,
+) -> Result<(), Error> {
+    match format {
+        Format::Xlsx => xlsx::write_orders(
+            path,
+            orders,
+            None,
+            &TradeMap::default(),
+            &Default::default(),
+            &[],
+            layout,
+        )
+        .map_err(|e| Error::Write(Box::new(e)))?,
+        Format::Arrow => feather::write_orders(path, orders, layout.get_source())
+            .map_err(|e| Error::Write(Box::new(e)))?,
+        Format::Csv => csv::write_orders(path, orders, layout)?,
+        Format::Json => json::write_orders(path, orders, layout)?,
+        Format::Sqlite => {
+            sqlite::write_orders(path, orders).map_err(|e| Error::Write(Box::new(e)))?
+        }
+        Format::Parquet => parquet::write_orders(path, orders, layout.get_source())
+            .map_err(|e| Error::Write(Box::new(e)))?,
+        Format::Beancount => beancount::write_orders(path, orders, &Default::default())?,
+        Format::Qif => qif::write_orders(path, orders)?,
+    }
+    Ok(())
+}
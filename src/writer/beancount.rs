@@ -0,0 +1,215 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::date;
+use crate::{DeliveryOrder, Trade};
+
+// the amounts less than it are rounding noise
+// 0.005
+const EPSILON: Decimal = Decimal::from_parts(5, 0, 0, false, 3);
+
+/// Account names of the beancount output, set in `[beancount]` of config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Accounts {
+    /// cash of the brokerage account
+    cash: String,
+    /// the securities held, one commodity per 证券代码
+    securities: String,
+    fees: String,
+    /// the other side of 银证转入 and 银证转出
+    bank: String,
+    /// dividends, interest and other cash received
+    income: String,
+    /// realized profit and loss of sells
+    pnl: String,
+    currency: String,
+    /// prefix of commodity, beancount commodities can't start with a digit
+    prefix: String,
+}
+
+impl Default for Accounts {
+    fn default() -> Self {
+        Self {
+            cash: "Assets:Broker:Cash".to_owned(),
+            securities: "Assets:Broker:Securities".to_owned(),
+            fees: "Expenses:Broker:Fees".to_owned(),
+            bank: "Assets:Bank".to_owned(),
+            income: "Income:Broker".to_owned(),
+            pnl: "Income:Broker:PnL".to_owned(),
+            currency: "CNY".to_owned(),
+            prefix: "CN".to_owned(),
+        }
+    }
+}
+
+impl Accounts {
+    fn commodity_of(&self, code: &str) -> String {
+        let code = code.trim().to_uppercase();
+
+        if code.starts_with(|v: char| v.is_ascii_alphabetic()) {
+            code
+        } else {
+            format!("{}{}", self.prefix, code)
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Postings of order, (account, amount), the amount is None for the posting
+/// balanced by beancount.
+fn postings_of(order: &DeliveryOrder, accounts: &Accounts) -> Vec<(String, Option<String>)> {
+    let currency = &accounts.currency;
+    let count = order.get_count();
+    let price = order.get_prize().unwrap_or_default();
+    let amount = order.get_amount().unwrap_or_default();
+    let commodity = accounts.commodity_of(order.get_code());
+    let cash = (
+        accounts.cash.clone(),
+        Some(format!("{:.2} {}", amount, currency)),
+    );
+    // the difference of amount and quantity × price, the fees mostly
+    let fees = -amount - count * price;
+    let mut postings = vec![];
+
+    match order.get_trade() {
+        Trade::Buy => {
+            postings.push((
+                accounts.securities.clone(),
+                Some(format!(
+                    "{} {} {{{} {}}}",
+                    count, commodity, price, currency
+                )),
+            ));
+            if fees.abs() >= EPSILON {
+                postings.push((
+                    accounts.fees.clone(),
+                    Some(format!("{:.2} {}", fees, currency)),
+                ));
+            }
+            postings.push(cash);
+        }
+        Trade::Sell => {
+            postings.push((
+                accounts.securities.clone(),
+                Some(format!(
+                    "{} {} {{}} @ {} {}",
+                    count, commodity, price, currency
+                )),
+            ));
+            if fees.abs() >= EPSILON {
+                postings.push((
+                    accounts.fees.clone(),
+                    Some(format!("{:.2} {}", fees, currency)),
+                ));
+            }
+            postings.push(cash);
+            postings.push((accounts.pnl.clone(), None));
+        }
+        Trade::In | Trade::Out => {
+            postings.push(cash);
+            postings.push((accounts.bank.clone(), None));
+        }
+        Trade::Dividend | Trade::Interest => {
+            postings.push(cash);
+            postings.push((accounts.income.clone(), None));
+        }
+        Trade::Fee => {
+            postings.push(cash);
+            postings.push((accounts.fees.clone(), None));
+        }
+        Trade::BonusShares => {
+            postings.push((
+                accounts.securities.clone(),
+                Some(format!("{} {} {{0 {}}}", count, commodity, currency)),
+            ));
+        }
+        Trade::Ignore => {}
+        Trade::Custom(_) if count.is_sign_negative() && !count.is_zero() => {
+            // the lots are reduced by the booking method, the cost goes to pnl
+            postings.push((
+                accounts.securities.clone(),
+                Some(format!("{} {} {{}}", count, commodity)),
+            ));
+            if !amount.is_zero() {
+                postings.push(cash);
+            }
+            postings.push((accounts.pnl.clone(), None));
+        }
+        Trade::Custom(_) => {
+            if !count.is_zero() {
+                postings.push((
+                    accounts.securities.clone(),
+                    Some(format!("{} {} {{0 {}}}", count, commodity, currency)),
+                ));
+            }
+            if !amount.is_zero() {
+                postings.push(cash);
+                postings.push((accounts.income.clone(), None));
+            }
+        }
+    }
+    postings
+}
+
+/// Write the orders as beancount transactions, a cash leg against a security leg.
+///
+/// The accounts used are opened at the date of first order.
+pub fn write_orders(
+    path: &str,
+    orders: &[DeliveryOrder],
+    accounts: &Accounts,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let transactions: Vec<(String, &DeliveryOrder, Vec<(String, Option<String>)>)> = orders
+        .iter()
+        .map(|order| {
+            (
+                date::format_date(order.get_date(), Some("%Y-%m-%d")),
+                order,
+                postings_of(order, accounts),
+            )
+        })
+        .filter(|(_, _, postings)| !postings.is_empty())
+        .collect();
+    let opened: BTreeSet<&String> = transactions
+        .iter()
+        .flat_map(|(_, _, postings)| postings.iter().map(|(account, _)| account))
+        .collect();
+
+    writeln!(
+        writer,
+        "option \"operating_currency\" \"{}\"\n",
+        accounts.currency
+    )?;
+    if let Some(first) = transactions.iter().map(|(date, _, _)| date).min() {
+        for account in opened {
+            writeln!(writer, "{} open {}", first, account)?;
+        }
+        writeln!(writer)?;
+    }
+    for (date, order, postings) in transactions.iter() {
+        writeln!(
+            writer,
+            "{} * \"{}\" \"{} {}\"",
+            date,
+            escape(order.get_name()),
+            escape(order.get_kind()),
+            escape(order.get_code())
+        )?;
+        for (account, amount) in postings {
+            match amount {
+                Some(amount) => writeln!(writer, "  {}  {}", account, amount)?,
+                None => writeln!(writer, "  {}", account)?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
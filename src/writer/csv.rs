@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::date;
+use crate::htsc;
+use crate::layout::Layout;
+use crate::number;
+use crate::report::ClosedLot;
+use crate::DeliveryOrder;
+
+/// Quote the field if it has comma, quote or line break.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_row<W: Write>(writer: &mut W, fields: &[String]) -> std::io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|v| quote(v)).collect();
+
+    writeln!(writer, "{}", fields.join(","))
+}
+
+/// Write the orders as UTF-8 CSV, the columns are same as the order sheet of xlsx.
+pub fn write_orders(path: &str, orders: &[DeliveryOrder], layout: &Layout) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut title = htsc::Context::gen_title();
+
+    if layout.get_source() {
+        title.extend(crate::SOURCE_TITLE.map(|v| v.to_owned()));
+    }
+    if layout.get_fees() {
+        title.extend(crate::FEE_TITLE.map(|v| v.to_owned()));
+    }
+    if layout.get_account() {
+        title.push(crate::ACCOUNT_TITLE.to_owned());
+    }
+    write_row(&mut writer, &title)?;
+    for order in orders {
+        let mut fields = vec![
+            date::format_date(order.get_date(), layout.get_date_format()),
+            order.get_code().clone(),
+            order.get_name().clone(),
+            order.get_kind().clone(),
+            order.get_count().to_string(),
+            number::format_number(order.get_prize()),
+            number::format_number(order.get_amount()),
+            number::format_number(order.get_owned()),
+        ];
+
+        if layout.get_source() {
+            fields.push(order.get_source().clone());
+            fields.push(order.get_line().to_string());
+        }
+        if layout.get_fees() {
+            fields.push(number::format_number(order.get_commission()));
+            fields.push(number::format_number(order.get_stamp_tax()));
+            fields.push(number::format_number(order.get_transfer_fee()));
+        }
+        if layout.get_account() {
+            fields.push(order.get_account().clone());
+        }
+        write_row(&mut writer, &fields)?;
+    }
+    writer.flush()
+}
+
+/// Write the closed lots in the columns (a) to (h) of Form 8949.
+pub fn write_lots(path: &str, lots: &[ClosedLot]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let title = [
+        "Description of property",
+        "Date acquired",
+        "Date sold or disposed of",
+        "Proceeds",
+        "Cost or other basis",
+        "Code",
+        "Amount of adjustment",
+        "Gain or (loss)",
+    ];
+
+    write_row(&mut writer, &title.map(|v| v.to_owned()))?;
+    for lot in lots {
+        let washed = !lot.get_adjustment().is_zero();
+
+        write_row(
+            &mut writer,
+            &[
+                format!("{} sh. {}", lot.get_count().normalize(), lot.get_code()),
+                lot.get_acquired().format("%m/%d/%Y").to_string(),
+                lot.get_sold().format("%m/%d/%Y").to_string(),
+                lot.get_proceeds().to_string(),
+                lot.get_basis().to_string(),
+                if washed { "W" } else { "" }.to_owned(),
+                if washed {
+                    lot.get_adjustment().to_string()
+                } else {
+                    String::default()
+                },
+                lot.get_gain().to_string(),
+            ],
+        )?;
+    }
+    writer.flush()
+}
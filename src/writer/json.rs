@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::date;
+use crate::layout::Layout;
+use crate::DeliveryOrder;
+
+/// The output path stands for standard output.
+pub const STDOUT_PATH: &'static str = "-";
+
+/// Write the orders as NDJSON, one object per line, to file `path` or standard output.
+pub fn write_orders(path: &str, orders: &[DeliveryOrder], layout: &Layout) -> std::io::Result<()> {
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(if path == STDOUT_PATH {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(File::create(path)?)
+    });
+
+    for order in orders {
+        let mut order = order.clone();
+
+        order.set_date(date::format_date(
+            order.get_date(),
+            layout.get_date_format(),
+        ));
+        serde_json::to_writer(&mut writer, &order)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float64Array, StringArray, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::date;
+use crate::DeliveryOrder;
+
+// rows of a row group, each group is written from one record batch
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+const DECIMAL_PRECISION: u8 = 18;
+const PRICE_SCALE: i8 = 3;
+const AMOUNT_SCALE: i8 = 2;
+
+/// Schema of the Parquet output, the dates and money are typed instead of text.
+pub fn schema(with_source: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("date", DataType::Date32, true),
+        Field::new("code", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        // fractional shares of overseas brokers
+        Field::new("count", DataType::Float64, true),
+        Field::new(
+            "price",
+            DataType::Decimal128(DECIMAL_PRECISION, PRICE_SCALE),
+            true,
+        ),
+        Field::new(
+            "amount",
+            DataType::Decimal128(DECIMAL_PRECISION, AMOUNT_SCALE),
+            true,
+        ),
+        Field::new(
+            "fee",
+            DataType::Decimal128(DECIMAL_PRECISION, AMOUNT_SCALE),
+            true,
+        ),
+        Field::new("owned", DataType::Float64, true),
+        Field::new("account", DataType::Utf8, false),
+        Field::new("margin", DataType::Boolean, false),
+    ];
+
+    if with_source {
+        fields.push(Field::new("source", DataType::Utf8, false));
+        fields.push(Field::new("line", DataType::UInt64, false));
+    }
+    Schema::new(fields)
+}
+
+/// Days since the epoch, the unit of [`DataType::Date32`].
+fn days_of(value: &str) -> Option<i32> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+
+    date::parse_date(value).map(|v| (v - epoch).num_days() as i32)
+}
+
+fn decimal_of(value: Option<Decimal>, scale: i8) -> Option<i128> {
+    let mut value = value?;
+
+    value.rescale(scale as u32);
+    Some(value.mantissa())
+}
+
+fn to_record_batch(
+    schema: &Arc<Schema>,
+    orders: &[DeliveryOrder],
+    with_source: bool,
+) -> Result<RecordBatch, ParquetError> {
+    let text = |get: fn(&DeliveryOrder) -> &String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(
+            orders.iter().map(|v| get(v).as_str()),
+        ))
+    };
+    let float = |get: fn(&DeliveryOrder) -> Option<Decimal>| -> ArrayRef {
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| get(v).and_then(|v| v.to_f64()))
+                .collect::<Float64Array>(),
+        )
+    };
+    let decimal =
+        |get: fn(&DeliveryOrder) -> Option<Decimal>, scale: i8| -> Result<ArrayRef, ParquetError> {
+            Ok(Arc::new(
+                orders
+                    .iter()
+                    .map(|v| decimal_of(get(v), scale))
+                    .collect::<Decimal128Array>()
+                    .with_precision_and_scale(DECIMAL_PRECISION, scale)?,
+            ))
+        };
+
+    let mut columns = vec![
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| days_of(v.get_date()))
+                .collect::<Date32Array>(),
+        ) as ArrayRef,
+        text(DeliveryOrder::get_code),
+        text(DeliveryOrder::get_name),
+        text(DeliveryOrder::get_kind),
+        Arc::new(
+            orders
+                .iter()
+                .map(|v| v.get_count().to_f64())
+                .collect::<Float64Array>(),
+        ),
+        decimal(DeliveryOrder::get_prize, PRICE_SCALE)?,
+        decimal(DeliveryOrder::get_amount, AMOUNT_SCALE)?,
+        decimal(DeliveryOrder::get_fee, AMOUNT_SCALE)?,
+        float(DeliveryOrder::get_owned),
+        text(DeliveryOrder::get_account),
+        Arc::new(BooleanArray::from_iter(
+            orders.iter().map(|v| Some(v.is_margin())),
+        )),
+    ];
+
+    if with_source {
+        columns.push(text(DeliveryOrder::get_source));
+        columns.push(Arc::new(UInt64Array::from_iter_values(
+            orders.iter().map(|v| v.get_line() as u64),
+        )));
+    }
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Write the orders into a Parquet file, for loading by DuckDB or Polars.
+///
+/// The orders are written in batches, one row group per batch.
+pub fn write_orders(
+    path: &str,
+    orders: &[DeliveryOrder],
+    with_source: bool,
+) -> Result<(), ParquetError> {
+    let schema = Arc::new(schema(with_source));
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(ROW_GROUP_SIZE)
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(props))?;
+
+    for orders in orders.chunks(ROW_GROUP_SIZE) {
+        writer.write(&to_record_batch(&schema, orders, with_source)?)?;
+    }
+    writer.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use rust_decimal::Decimal;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::date;
+use crate::{DeliveryOrder, Trade};
+
+/// The QIF action of order, None if it is not a transaction of investment account.
+fn action_of(order: &DeliveryOrder, count: Decimal, amount: Decimal) -> Option<&'static str> {
+    match order.get_trade() {
+        Trade::Buy => Some("Buy"),
+        Trade::Sell => Some("Sell"),
+        Trade::In => Some("XIn"),
+        Trade::Out => Some("XOut"),
+        Trade::Dividend => Some("Div"),
+        Trade::Interest => Some("IntInc"),
+        Trade::Fee => Some("MiscExp"),
+        Trade::BonusShares => Some("ShrsIn"),
+        Trade::Ignore => None,
+        // shares allotted without payment, such as 红股入账
+        Trade::Custom(_) if count > Decimal::ZERO && amount.is_zero() => Some("ShrsIn"),
+        Trade::Custom(_) if count < Decimal::ZERO && amount.is_zero() => Some("ShrsOut"),
+        Trade::Custom(_) if amount > Decimal::ZERO => Some("MiscInc"),
+        Trade::Custom(_) if amount < Decimal::ZERO => Some("MiscExp"),
+        Trade::Custom(_) => None,
+    }
+}
+
+/// Write the orders as QIF investment transactions, which most personal
+/// finance apps can import.
+pub fn write_orders(path: &str, orders: &[DeliveryOrder]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "!Type:Invst")?;
+    for order in orders {
+        let count = order.get_count();
+        let price = order.get_prize().unwrap_or_default();
+        let amount = order.get_amount().unwrap_or_default();
+        let action = match action_of(order, count, amount) {
+            Some(action) => action,
+            None => continue,
+        };
+
+        writeln!(
+            writer,
+            "D{}",
+            date::format_date(order.get_date(), Some("%m/%d/%Y"))
+        )?;
+        writeln!(writer, "N{}", action)?;
+        if !order.get_code().is_empty() {
+            writeln!(writer, "Y{}", order.get_code())?;
+        }
+        if !count.is_zero() {
+            writeln!(writer, "I{}", price)?;
+            writeln!(writer, "Q{}", count.abs())?;
+        }
+        writeln!(writer, "T{:.2}", amount.abs())?;
+        if matches!(order.get_trade(), Trade::Buy | Trade::Sell) {
+            // the difference of amount and quantity × price, the fees mostly
+            let fees = (amount.abs() - count.abs() * price).abs();
+
+            if fees >= Decimal::new(5, 3) {
+                writeln!(writer, "O{:.2}", fees)?;
+            }
+        }
+        writeln!(writer, "M{} {}", order.get_kind(), order.get_name())?;
+        writeln!(writer, "^")?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,110 @@
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::DeliveryOrder;
+
+const TABLE: &'static str = "delivery_order";
+
+// the money is stored as text, REAL can't keep the decimal places exactly
+fn to_text(value: Option<Decimal>) -> Option<String> {
+    value.map(|v| v.normalize().to_string())
+}
+
+/// The key of `order` in its account, the 成交编号 if the broker has it, or the values of order.
+fn key_of(order: &DeliveryOrder) -> String {
+    if order.get_trade_no().is_empty() {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            order.get_date(),
+            order.get_code(),
+            order.get_kind(),
+            order.get_count().normalize(),
+            to_text(order.get_prize()).unwrap_or_default(),
+            to_text(order.get_amount()).unwrap_or_default(),
+        )
+    } else {
+        format!("#{}", order.get_trade_no())
+    }
+}
+
+/// Create the table of orders if not exists.
+///
+/// The rows are keyed by the account, the key of order and its ordinal among the
+/// orders of same key in one import, so the same statement imported again replaces
+/// the rows instead of doubling them, while the identical fills are kept apart.
+/// The missing values are NULL.
+fn create_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            date TEXT NOT NULL,
+            code TEXT NOT NULL,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            count TEXT NOT NULL,
+            price TEXT,
+            amount TEXT,
+            fee TEXT,
+            owned TEXT,
+            trade_no TEXT,
+            account TEXT NOT NULL,
+            source TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            ordinal INTEGER NOT NULL,
+            UNIQUE (account, key, ordinal)
+        );
+        CREATE INDEX IF NOT EXISTS {table}_code_date ON {table} (code, date);",
+        table = TABLE
+    ))
+}
+
+/// Write the orders into SQLite database `path`, the rows of previous runs are kept.
+pub fn write_orders(path: &str, orders: &[DeliveryOrder]) -> rusqlite::Result<()> {
+    let mut conn = Connection::open(path)?;
+    // (account, key) => count of orders
+    let mut ordinals: HashMap<(&String, String), i64> = HashMap::new();
+
+    create_table(&conn)?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(&format!(
+            "INSERT INTO {} (date, code, name, kind, count, price, amount, fee, owned, trade_no,
+                account, source, line, key, ordinal)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+             ON CONFLICT (account, key, ordinal)
+             DO UPDATE SET date = excluded.date, code = excluded.code, name = excluded.name,
+                kind = excluded.kind, count = excluded.count, price = excluded.price,
+                amount = excluded.amount, fee = excluded.fee, owned = excluded.owned,
+                trade_no = excluded.trade_no, source = excluded.source, line = excluded.line",
+            TABLE
+        ))?;
+
+        for order in orders {
+            let key = key_of(order);
+            let ordinal = ordinals
+                .entry((order.get_account(), key.clone()))
+                .or_default();
+
+            *ordinal += 1;
+            stmt.execute(params![
+                order.get_date(),
+                order.get_code(),
+                order.get_name(),
+                order.get_kind(),
+                order.get_count().normalize().to_string(),
+                to_text(order.get_prize()),
+                to_text(order.get_amount()),
+                to_text(order.get_fee()),
+                to_text(order.get_owned()),
+                Some(order.get_trade_no()).filter(|v| !v.is_empty()),
+                order.get_account(),
+                order.get_source(),
+                order.get_line() as i64,
+                key,
+                *ordinal,
+            ])?;
+        }
+    }
+    tx.commit()
+}
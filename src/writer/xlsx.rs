@@ -0,0 +1,882 @@
+use calamine::{DataType, Range};
+use chrono::Datelike;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_xlsxwriter::{
+    Color, ExcelDateTime, Format, FormatAlign, Formula, Note, Workbook, Worksheet, XlsxError,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::audit::Audit;
+use crate::date;
+use crate::error::Malformed;
+use crate::holding;
+use crate::htsc;
+use crate::layout::Layout;
+use crate::mapping::TradeMap;
+use crate::round::Rounding;
+use crate::template::{CellStyle, SheetStyle, Template};
+use crate::{DeliveryOrder, Trade, ACCOUNT_TITLE, BALANCE_TITLE, FEE_TITLE, SOURCE_TITLE};
+
+const ORDER_SHEET: &'static str = "交割单";
+const POSITION_SHEET: &'static str = "持仓变化";
+const HOLDING_SHEET: &'static str = "持仓";
+const CASH_SHEET: &'static str = "资金";
+const AUDIT_SHEET: &'static str = "异常";
+const ERROR_SHEET: &'static str = "错误行";
+const PIVOT_SHEET: &'static str = "月度汇总";
+// the last row index of an excel sheet
+const MAX_ROW: u32 = 1_048_575;
+// the max length of sheet name
+const MAX_NAME: usize = 31;
+// format of the dates if no `--date-format` given
+const DATE_FORMAT: &'static str = "%Y-%m-%d";
+const COUNT_FORMAT: &'static str = "#,##0";
+// decimal places of the number formats if not set in `[round]`
+const PRICE_PLACES: u32 = 3;
+const AMOUNT_PLACES: u32 = 2;
+
+/// Number formats of the columns in order sheet.
+struct Formats {
+    date: Format,
+    count: Format,
+    price: Format,
+    amount: Format,
+    fee: Format,
+    // column => format of the title in template
+    title: HashMap<u16, Format>,
+    // column => format of the first row in template
+    columns: HashMap<u16, Format>,
+}
+
+impl Formats {
+    fn new(date_format: Option<&str>, rounding: &Rounding) -> Self {
+        let [price, amount, fee] = number_formats(rounding);
+
+        Self {
+            date: self::date_format(date_format),
+            count: Format::new().set_num_format(COUNT_FORMAT),
+            price: Format::new().set_num_format(price),
+            amount: Format::new().set_num_format(amount),
+            fee: Format::new().set_num_format(fee),
+            title: HashMap::new(),
+            columns: HashMap::new(),
+        }
+    }
+
+    /// Take the formats of title and first row from the order sheet `style` of template,
+    /// the number format in `numbers` is used if the template cell has none.
+    fn with_template(
+        mut self,
+        template: &Template,
+        style: &SheetStyle,
+        numbers: &[(u16, String)],
+    ) -> Self {
+        for ((row, col), idx) in style.get_cells() {
+            let cell = match template.get_styles().get(*idx) {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let number = numbers
+                .iter()
+                .find(|(v, _)| v == col)
+                .map(|(_, v)| v.as_str());
+
+            match row {
+                0 => {
+                    self.title.insert(*col, cell_format(cell, None));
+                }
+                1 => {
+                    self.columns.insert(*col, cell_format(cell, number));
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Format of column `col`, the template wins over `default`.
+    fn column<'a>(&'a self, col: u16, default: &'a Format) -> &'a Format {
+        self.columns.get(&col).unwrap_or(default)
+    }
+
+    /// Format of the text column `col`, `None` if the template has none.
+    fn text(&self, col: u16) -> Option<&Format> {
+        self.columns.get(&col)
+    }
+}
+
+/// Number formats of price, amount and fee with the decimal places of `rounding`,
+/// the fee follows the amount if its places is not set.
+fn number_formats(rounding: &Rounding) -> [String; 3] {
+    let amount = rounding.get_amount().unwrap_or(AMOUNT_PLACES);
+
+    [
+        rounding.get_price().unwrap_or(PRICE_PLACES),
+        amount,
+        rounding.get_fee().unwrap_or(amount),
+    ]
+    .map(|places| match places {
+        0 => COUNT_FORMAT.to_owned(),
+        places => format!("{}.{}", COUNT_FORMAT, "0".repeat(places as usize)),
+    })
+}
+
+/// Translate the cell `style` of template, the `number` format is used if the style has none.
+fn cell_format(style: &CellStyle, number: Option<&str>) -> Format {
+    let mut format = Format::new();
+
+    match (style.get_num_format(), style.get_num_format_index(), number) {
+        (Some(num_format), _, _) => format = format.set_num_format(num_format),
+        (None, 0, Some(num_format)) => format = format.set_num_format(num_format),
+        (None, 0, None) => {}
+        (None, index, _) => format = format.set_num_format_index(index),
+    }
+    if style.get_bold() {
+        format = format.set_bold();
+    }
+    if style.get_italic() {
+        format = format.set_italic();
+    }
+    if let Some(name) = style.get_font_name() {
+        format = format.set_font_name(name);
+    }
+    if let Some(size) = style.get_font_size() {
+        format = format.set_font_size(size);
+    }
+    if let Some(color) = style.get_font_color() {
+        format = format.set_font_color(Color::RGB(color));
+    }
+    if let Some(fill) = style.get_fill() {
+        format = format.set_background_color(Color::RGB(fill));
+    }
+    let align = match style.get_align() {
+        Some("left") => Some(FormatAlign::Left),
+        Some("center") => Some(FormatAlign::Center),
+        Some("right") => Some(FormatAlign::Right),
+        Some("fill") => Some(FormatAlign::Fill),
+        Some("justify") => Some(FormatAlign::Justify),
+        Some("centerContinuous") => Some(FormatAlign::CenterAcross),
+        Some("distributed") => Some(FormatAlign::Distributed),
+        _ => None,
+    };
+    let valign = match style.get_valign() {
+        Some("top") => Some(FormatAlign::Top),
+        Some("center") => Some(FormatAlign::VerticalCenter),
+        Some("bottom") => Some(FormatAlign::Bottom),
+        Some("justify") => Some(FormatAlign::VerticalJustify),
+        Some("distributed") => Some(FormatAlign::VerticalDistributed),
+        _ => None,
+    };
+
+    for align in [align, valign].into_iter().flatten() {
+        format = format.set_align(align);
+    }
+    if style.get_wrap() {
+        format = format.set_text_wrap();
+    }
+    format
+}
+
+/// Copy the column widths and frozen panes of the template sheet `style`.
+fn set_layout(sheet: &mut Worksheet, style: &SheetStyle) -> Result<(), XlsxError> {
+    for (first, last, width) in style.get_widths() {
+        sheet.set_column_range_width(*first, *last, *width)?;
+    }
+    if let Some((row, col)) = style.get_pane() {
+        sheet.set_freeze_panes(row, col)?;
+    }
+    Ok(())
+}
+
+/// Write the orders into workbook for 投资账本, with the sheets enabled by `layout`.
+///
+/// The sheets of `template` go first with their formatting, and the order sheets
+/// follow the formatting of its order sheet. The `audit` and `malformed` lines are
+/// written in their own sheets if not empty.
+pub fn write_orders(
+    path: &str,
+    orders: &[DeliveryOrder],
+    template: Option<&Template>,
+    trades: &TradeMap,
+    audit: &Audit,
+    malformed: &[Malformed],
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let position = layout.get_position();
+    let mut workbook = Workbook::new();
+    // (date, code, account) => (name, owned after the last trade of that day)
+    let mut positions: BTreeMap<(String, String, String), (String, Option<Decimal>)> =
+        BTreeMap::new();
+    let mut groups: BTreeMap<String, Vec<&DeliveryOrder>> = BTreeMap::new();
+
+    // sheets of template go first
+    if let Some(template) = template {
+        let formats: Vec<Format> = template
+            .get_styles()
+            .iter()
+            .map(|v| cell_format(v, None))
+            .collect();
+
+        for (name, range, style) in template.get_sheets() {
+            let formulas = template.get_formulas(name);
+
+            write_value_sheet(&mut workbook, name, range, formulas, style, &formats)?;
+        }
+    }
+    write_order_sheet(&mut workbook, ORDER_SHEET, orders.iter(), template, layout)?;
+    for order in orders.iter() {
+        if let Some(name) = layout.get_group_by().sheet_of(order) {
+            groups.entry(name).or_default().push(order);
+        }
+    }
+    for (name, orders) in groups {
+        write_order_sheet(&mut workbook, &name, orders.into_iter(), template, layout)?;
+    }
+    if position {
+        for order in orders.iter() {
+            positions.insert(
+                (
+                    order.get_date().clone(),
+                    order.get_code().clone(),
+                    order.get_account().clone(),
+                ),
+                (order.get_name().clone(), order.get_owned()),
+            );
+        }
+    }
+
+    if position {
+        write_position_sheet(&mut workbook, &positions, layout)?;
+    }
+    if layout.get_holding() {
+        write_holding_sheet(&mut workbook, orders, layout)?;
+    }
+    if layout.get_cash() {
+        write_cash_sheet(&mut workbook, orders, trades, layout)?;
+    }
+    if layout.get_pivot() {
+        write_pivot_sheet(&mut workbook, orders, layout)?;
+    }
+    // the audit trail travels with the data
+    if !audit.is_empty() {
+        write_audit_sheet(&mut workbook, audit, layout)?;
+    }
+    if !malformed.is_empty() {
+        write_error_sheet(&mut workbook, malformed, layout)?;
+    }
+
+    workbook.save(path)
+}
+
+/// Write the orders into sheet `name`, roll over to a new sheet `name-N` when current sheet is full.
+///
+/// The names used by other sheets are skipped, see [`unused_name`]. The formatting of
+/// order sheet in `template` is applied to every sheet.
+fn write_order_sheet<'a>(
+    workbook: &mut Workbook,
+    name: &str,
+    orders: impl Iterator<Item = &'a DeliveryOrder>,
+    template: Option<&Template>,
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let mut title = htsc::Context::gen_title();
+    let constant_memory = layout.get_constant_memory();
+    let first = unused_name(workbook, name);
+    let mut sheet = add_sheet(workbook, &first, constant_memory)?;
+    let style = template.and_then(|v| v.get_orders());
+    let mut counter = 0;
+
+    if layout.get_source() {
+        title.extend(SOURCE_TITLE.map(|v| v.to_owned()));
+    }
+    // the fee columns follow the source columns
+    let fee_column = title.len() as u16;
+
+    if layout.get_fees() {
+        title.extend(FEE_TITLE.map(|v| v.to_owned()));
+    }
+    let account_column = title.len() as u16;
+
+    if layout.get_account() {
+        title.push(ACCOUNT_TITLE.to_owned());
+    }
+    let mut formats = Formats::new(layout.get_date_format(), layout.get_rounding());
+
+    if let (Some(template), Some(style)) = (template, style) {
+        let date = date::excel_format(layout.get_date_format().unwrap_or(DATE_FORMAT));
+        let [price, amount, fee] = number_formats(layout.get_rounding());
+        let mut numbers = vec![
+            (0, date),
+            (4, COUNT_FORMAT.to_owned()),
+            (5, price),
+            (6, amount),
+            (7, COUNT_FORMAT.to_owned()),
+        ];
+
+        if layout.get_fees() {
+            numbers.extend((0..FEE_TITLE.len()).map(|v| (fee_column + v as u16, fee.clone())));
+        }
+        formats = formats.with_template(template, style, &numbers);
+    }
+    write_title(sheet, &title, &formats, style)?;
+    for order in orders {
+        if counter == MAX_ROW {
+            let next = unused_name(workbook, name);
+
+            sheet = add_sheet(workbook, &next, constant_memory)?;
+            counter = 0;
+            write_title(sheet, &title, &formats, style)?;
+        }
+        counter += 1;
+        write_order(sheet, counter, order, &formats)?;
+        if layout.get_source() {
+            write_text(sheet, counter, 8, order.get_source(), formats.text(8))?;
+            match formats.text(9) {
+                Some(format) => {
+                    sheet.write_number_with_format(counter, 9, order.get_line() as f64, format)?
+                }
+                None => sheet.write_number(counter, 9, order.get_line() as f64)?,
+            };
+        }
+        if layout.get_fees() {
+            for (idx, fee) in [
+                order.get_commission(),
+                order.get_stamp_tax(),
+                order.get_transfer_fee(),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let col = fee_column + idx as u16;
+
+                write_decimal(sheet, counter, col, fee, formats.column(col, &formats.fee))?;
+            }
+        }
+        if layout.get_account() {
+            write_text(
+                sheet,
+                counter,
+                account_column,
+                order.get_account(),
+                formats.text(account_column),
+            )?;
+        }
+        if layout.get_comments() && !order.get_source().is_empty() {
+            sheet.insert_note(
+                counter,
+                0,
+                &Note::new(format!("{}:{}", order.get_source(), order.get_line())),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the characters excel doesn't allow in sheet name with `_`, and trim the apostrophes.
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|v| match v {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            v => v,
+        })
+        .collect();
+    let name = name.trim_matches('\'');
+
+    if name.is_empty() {
+        "_".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Return `name`, or `name-N` with the least N from 2 if the name is used by the sheets of `workbook`.
+///
+/// The sheet names of excel are case insensitive and limited to 31 characters, the
+/// invalid characters are replaced, see [`sanitize_name`].
+fn unused_name(workbook: &mut Workbook, name: &str) -> String {
+    let name = sanitize_name(name);
+    let name = name.as_str();
+    let used: HashSet<String> = workbook
+        .worksheets()
+        .iter()
+        .map(|v| v.name().to_lowercase())
+        .collect();
+    let mut next: String = name.chars().take(MAX_NAME).collect();
+    // the name may end with apostrophe after cut
+    next.truncate(next.trim_end_matches('\'').len());
+    let mut counter = 1;
+
+    while used.contains(&next.to_lowercase()) {
+        counter += 1;
+        let suffix = format!("-{}", counter);
+
+        next = name
+            .chars()
+            .take(MAX_NAME - suffix.len())
+            .chain(suffix.chars())
+            .collect();
+    }
+    next
+}
+
+/// Add a sheet named `name`.
+///
+/// In constant memory mode every row is flushed to disk once the next row is written.
+fn add_sheet<'a>(
+    workbook: &'a mut Workbook,
+    name: &str,
+    constant_memory: bool,
+) -> Result<&'a mut Worksheet, XlsxError> {
+    let sheet = if constant_memory {
+        workbook.add_worksheet_with_constant_memory()
+    } else {
+        workbook.add_worksheet()
+    };
+
+    sheet.set_name(name)?;
+    Ok(sheet)
+}
+
+/// Write the `title` and one row per item of `rows` by `write` into sheet `name`.
+///
+/// Same as the order sheet, the rows roll over to a new sheet with the title when
+/// current sheet is full, and the sheets are added in constant memory mode if enabled.
+fn write_rows<T>(
+    workbook: &mut Workbook,
+    name: &str,
+    title: &[&str],
+    rows: impl IntoIterator<Item = T>,
+    constant_memory: bool,
+    mut write: impl FnMut(&mut Worksheet, u32, T) -> Result<(), XlsxError>,
+) -> Result<(), XlsxError> {
+    let first = unused_name(workbook, name);
+    let mut sheet = add_sheet(workbook, &first, constant_memory)?;
+    let mut counter = 0;
+
+    for idx in 0..title.len() {
+        sheet.write_string(counter, idx as u16, title[idx])?;
+    }
+    for row in rows {
+        if counter == MAX_ROW {
+            let next = unused_name(workbook, name);
+
+            sheet = add_sheet(workbook, &next, constant_memory)?;
+            counter = 0;
+            for idx in 0..title.len() {
+                sheet.write_string(counter, idx as u16, title[idx])?;
+            }
+        }
+        counter += 1;
+        write(sheet, counter, row)?;
+    }
+
+    Ok(())
+}
+
+/// Write the title row, with the formatting of template order sheet `style`.
+fn write_title(
+    sheet: &mut Worksheet,
+    title: &[String],
+    formats: &Formats,
+    style: Option<&SheetStyle>,
+) -> Result<(), XlsxError> {
+    if let Some(style) = style {
+        set_layout(sheet, style)?;
+    }
+    for idx in 0..title.len() {
+        match formats.title.get(&(idx as u16)) {
+            Some(format) => sheet.write_string_with_format(0, idx as u16, &title[idx], format)?,
+            None => sheet.write_string(0, idx as u16, &title[idx])?,
+        };
+    }
+    Ok(())
+}
+
+/// Write the text `value`, with `format` if it is given.
+fn write_text(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &str,
+    format: Option<&Format>,
+) -> Result<(), XlsxError> {
+    match format {
+        Some(format) => sheet.write_string_with_format(row, col, value, format)?,
+        None => sheet.write_string(row, col, value)?,
+    };
+    Ok(())
+}
+
+/// Copy the values of `range` into a new sheet `name`, with the column widths, panes
+/// and cell formats of `style`, the `formats` are translated from the styles of template.
+fn write_value_sheet(
+    workbook: &mut Workbook,
+    name: &str,
+    range: &Range<DataType>,
+    formulas: Option<&HashMap<(u32, u16), String>>,
+    style: &SheetStyle,
+    formats: &[Format],
+) -> Result<(), XlsxError> {
+    let sheet = workbook.add_worksheet().set_name(name)?;
+    let (row_start, col_start) = range.start().unwrap_or((0, 0));
+    let default = Format::new();
+    let mut written = HashSet::new();
+
+    set_layout(sheet, style)?;
+    for (row, col, value) in range.used_cells() {
+        let row = row_start + row as u32;
+        let col = (col_start + col as u32) as u16;
+        let format = style
+            .get_cells()
+            .get(&(row, col))
+            .and_then(|v| formats.get(*v))
+            .unwrap_or(&default);
+
+        written.insert((row, col));
+        // keep the formulas, the value read is the result cached by excel
+        if let Some(formula) = formulas.and_then(|v| v.get(&(row, col))) {
+            sheet.write_formula_with_format(
+                row,
+                col,
+                Formula::new(formula).set_result(value.to_string()),
+                format,
+            )?;
+            continue;
+        }
+        match value {
+            DataType::Int(v) => {
+                sheet.write_number_with_format(row, col, *v as f64, format)?;
+            }
+            DataType::Float(v) | DataType::DateTime(v) => {
+                sheet.write_number_with_format(row, col, *v, format)?;
+            }
+            DataType::Bool(v) => {
+                sheet.write_boolean_with_format(row, col, *v, format)?;
+            }
+            DataType::Empty => {}
+            value => {
+                sheet.write_string_with_format(row, col, value.to_string(), format)?;
+            }
+        }
+    }
+    // the formulas of empty result
+    for ((row, col), formula) in formulas.into_iter().flatten() {
+        if written.insert((*row, *col)) {
+            let format = style
+                .get_cells()
+                .get(&(*row, *col))
+                .and_then(|v| formats.get(*v))
+                .unwrap_or(&default);
+
+            sheet.write_formula_with_format(*row, *col, Formula::new(formula), format)?;
+        }
+    }
+    // the styled cells without value, e.g. the filled blanks
+    for ((row, col), idx) in style.get_cells() {
+        if let (false, Some(format)) = (written.contains(&(*row, *col)), formats.get(*idx)) {
+            sheet.write_blank(*row, *col, format)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The number format of dates, translated from the chrono `format`.
+fn date_format(format: Option<&str>) -> Format {
+    Format::new().set_num_format(date::excel_format(format.unwrap_or(DATE_FORMAT)))
+}
+
+/// Write `value` as a date of Excel if it can be parsed, otherwise as it is.
+fn write_date(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &str,
+    format: &Format,
+) -> Result<(), XlsxError> {
+    match date::parse_date(value) {
+        Some(date) => {
+            let date =
+                ExcelDateTime::from_ymd(date.year() as u16, date.month() as u8, date.day() as u8)?;
+
+            sheet.write_datetime_with_format(row, col, &date, format)?;
+        }
+        None => {
+            sheet.write_string(row, col, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `value` as a number with `format`, the cell is left blank if it is not given.
+fn write_decimal(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: Option<Decimal>,
+    format: &Format,
+) -> Result<(), XlsxError> {
+    if let Some(number) = value.and_then(|v| v.to_f64()) {
+        sheet.write_number_with_format(row, col, number, format)?;
+    }
+    Ok(())
+}
+
+fn write_order(
+    sheet: &mut Worksheet,
+    row: u32,
+    order: &DeliveryOrder,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    write_date(
+        sheet,
+        row,
+        0,
+        order.get_date(),
+        formats.column(0, &formats.date),
+    )?;
+    write_text(sheet, row, 1, order.get_code(), formats.text(1))?;
+    write_text(sheet, row, 2, order.get_name(), formats.text(2))?;
+    write_text(sheet, row, 3, order.get_kind(), formats.text(3))?;
+    write_decimal(
+        sheet,
+        row,
+        4,
+        Some(order.get_count()),
+        formats.column(4, &formats.count),
+    )?;
+    write_decimal(
+        sheet,
+        row,
+        5,
+        order.get_prize(),
+        formats.column(5, &formats.price),
+    )?;
+    write_decimal(
+        sheet,
+        row,
+        6,
+        order.get_amount(),
+        formats.column(6, &formats.amount),
+    )?;
+    write_decimal(
+        sheet,
+        row,
+        7,
+        order.get_owned(),
+        formats.column(7, &formats.count),
+    )?;
+    Ok(())
+}
+
+/// One row per day, code and account, the holdings of each account are counted apart.
+fn write_position_sheet(
+    workbook: &mut Workbook,
+    positions: &BTreeMap<(String, String, String), (String, Option<Decimal>)>,
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let title = ["日期", "证券代码", "证券名称", "持仓数量", ACCOUNT_TITLE];
+    let date_format = self::date_format(layout.get_date_format());
+    let count_format = Format::new().set_num_format(COUNT_FORMAT);
+
+    write_rows(
+        workbook,
+        POSITION_SHEET,
+        &title,
+        positions.iter(),
+        layout.get_constant_memory(),
+        |sheet, row, ((date, code, account), (name, owned))| {
+            write_date(sheet, row, 0, date, &date_format)?;
+            sheet.write_string(row, 1, code)?;
+            sheet.write_string(row, 2, name)?;
+            write_decimal(sheet, row, 3, *owned, &count_format)?;
+            sheet.write_string(row, 4, account)?;
+            Ok(())
+        },
+    )
+}
+
+/// One row per account and code, the account column is written if enabled by `layout`.
+fn write_holding_sheet(
+    workbook: &mut Workbook,
+    orders: &[DeliveryOrder],
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let mut title = vec!["证券代码", "证券名称", "持仓数量", "平均成本", "持仓成本"];
+    let formats = Formats::new(None, layout.get_rounding());
+    let account = layout.get_account();
+
+    if account {
+        title.push(ACCOUNT_TITLE);
+    }
+    write_rows(
+        workbook,
+        HOLDING_SHEET,
+        &title,
+        holding::final_holdings(orders),
+        layout.get_constant_memory(),
+        |sheet, row, holding| {
+            sheet.write_string(row, 0, holding.get_code())?;
+            sheet.write_string(row, 1, holding.get_name())?;
+            sheet.write_number_with_format(row, 2, holding.get_count() as f64, &formats.count)?;
+            sheet.write_number_with_format(
+                row,
+                3,
+                holding.get_average_cost().to_f64().unwrap_or(0.0),
+                &formats.price,
+            )?;
+            sheet.write_number_with_format(
+                row,
+                4,
+                holding.get_cost().to_f64().unwrap_or(0.0),
+                &formats.amount,
+            )?;
+            if account {
+                sheet.write_string(row, 5, holding.get_account())?;
+            }
+            Ok(())
+        },
+    )
+}
+
+fn write_cash_sheet(
+    workbook: &mut Workbook,
+    orders: &[DeliveryOrder],
+    trades: &TradeMap,
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let title = [
+        "日期",
+        "证券代码",
+        "证券名称",
+        "交易类别",
+        "发生金额",
+        BALANCE_TITLE,
+    ];
+    let formats = Formats::new(layout.get_date_format(), layout.get_rounding());
+    let mut balance = layout.get_opening_cash();
+
+    write_rows(
+        workbook,
+        CASH_SHEET,
+        &title,
+        orders
+            .iter()
+            .filter(|v| trades.category(v.get_trade()).is_cash()),
+        layout.get_constant_memory(),
+        |sheet, row, order| {
+            let amount = order.get_amount().unwrap_or_default();
+
+            balance += amount;
+            write_date(sheet, row, 0, order.get_date(), &formats.date)?;
+            sheet.write_string(row, 1, order.get_code())?;
+            sheet.write_string(row, 2, order.get_name())?;
+            sheet.write_string(row, 3, order.get_kind())?;
+            write_decimal(sheet, row, 4, Some(amount), &formats.amount)?;
+            write_decimal(sheet, row, 5, Some(balance), &formats.amount)?;
+            Ok(())
+        },
+    )
+}
+
+/// One row per code and one column per month, the value is net bought amount.
+fn write_pivot_sheet(
+    workbook: &mut Workbook,
+    orders: &[DeliveryOrder],
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let mut months = BTreeSet::new();
+    // code => (name, month => net bought amount)
+    let mut pivot: BTreeMap<&String, (&String, HashMap<String, Decimal>)> = BTreeMap::new();
+
+    for order in orders
+        .iter()
+        .filter(|v| v.get_trade() == &Trade::Buy || v.get_trade() == &Trade::Sell)
+    {
+        let month = match date::parse_date(order.get_date()) {
+            Some(date) => date.format("%Y-%m").to_string(),
+            None => continue,
+        };
+        let amount = order.get_amount().unwrap_or_default();
+        let (name, amounts) = pivot
+            .entry(order.get_code())
+            .or_insert((order.get_name(), HashMap::new()));
+
+        *name = order.get_name();
+        // amount of buy is negative
+        *amounts.entry(month.clone()).or_default() -= amount;
+        months.insert(month);
+    }
+    let title: Vec<&str> = ["证券代码", "证券名称"]
+        .into_iter()
+        .chain(months.iter().map(|v| v.as_str()))
+        .collect();
+
+    write_rows(
+        workbook,
+        PIVOT_SHEET,
+        &title,
+        pivot.iter(),
+        layout.get_constant_memory(),
+        |sheet, row, (code, (name, amounts))| {
+            sheet.write_string(row, 0, *code)?;
+            sheet.write_string(row, 1, *name)?;
+            for (idx, month) in months.iter().enumerate() {
+                if let Some(amount) = amounts.get(month) {
+                    sheet.write_number(row, idx as u16 + 2, amount.to_f64().unwrap_or(0.0))?;
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+fn write_audit_sheet(
+    workbook: &mut Workbook,
+    audit: &Audit,
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let title = ["级别", "文件", "行号", "说明"];
+
+    write_rows(
+        workbook,
+        AUDIT_SHEET,
+        &title,
+        audit.get_records(),
+        layout.get_constant_memory(),
+        |sheet, row, record| {
+            sheet.write_string(row, 0, record.get_level().label())?;
+            sheet.write_string(row, 1, record.get_path())?;
+            sheet.write_number(row, 2, record.get_line() as f64)?;
+            sheet.write_string(row, 3, record.get_message())?;
+            Ok(())
+        },
+    )
+}
+
+/// The lines dropped by `--on-error report`.
+fn write_error_sheet(
+    workbook: &mut Workbook,
+    malformed: &[Malformed],
+    layout: &Layout,
+) -> Result<(), XlsxError> {
+    let title = ["文件", "行号", "错误", "内容"];
+
+    write_rows(
+        workbook,
+        ERROR_SHEET,
+        &title,
+        malformed,
+        layout.get_constant_memory(),
+        |sheet, row, record| {
+            sheet.write_string(row, 0, record.get_path())?;
+            sheet.write_number(row, 1, record.get_line() as f64)?;
+            sheet.write_string(row, 2, &record.get_error().to_string())?;
+            sheet.write_string(row, 3, record.get_content())?;
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,28 @@
+use crate::broker::BrokerParser;
+
+/// The delivery order exported by 招商证券, the columns are aligned by spaces
+/// and the fees are in separate columns like 佣金 and 印花税.
+///
+/// The column widths are inferred from the header, see [`crate::tokenizer::Dialect::detect`].
+#[derive(Debug)]
+pub struct Zhaoshang;
+
+impl BrokerParser for Zhaoshang {
+    fn get_name(&self) -> &'static str {
+        crate::ZHAOSHANG_TYPE
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.contains("印花税") && !line.contains(['\t', ','])
+    }
+
+    fn map_title(&self, title: &str) -> String {
+        match title {
+            "成交日期" => "发生日期",
+            "摘要" => "业务名称",
+            "股份余额" => "证券数量",
+            title => title,
+        }
+        .to_owned()
+    }
+}
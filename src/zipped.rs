@@ -0,0 +1,62 @@
+use glob::Pattern;
+use std::fs::File;
+use std::io::Read;
+use zip::result::ZipResult;
+use zip::ZipArchive;
+
+/// Separator between the path of zip file and the name of entry, e.g. `orders.zip!/202301.txt`.
+pub const SEPARATOR: &'static str = "!/";
+// the monthly statements in zip are text, the spreadsheet can't be read from memory
+const DEFAULT_PATTERNS: [&'static str; 2] = ["*.txt", "*.csv"];
+
+pub fn is_zip_path(path: &str) -> bool {
+    path.to_lowercase().ends_with(".zip")
+}
+
+/// Split the path of entry into the path of zip file and the name of entry.
+pub fn split_entry(path: &str) -> Option<(&str, &str)> {
+    path.split_once(SEPARATOR)
+}
+
+/// Return the file path on disk, the zip file for the entry path.
+pub fn real_path(path: &str) -> &str {
+    split_entry(path).map(|(zip, _)| zip).unwrap_or(path)
+}
+
+/// List the entries of zip file `path` matching the glob `pattern`, sorted by name.
+///
+/// The returned paths are used as input files, see [`read_entry`].
+pub fn entries(path: &str, pattern: Option<&str>) -> ZipResult<Vec<String>> {
+    let patterns: Vec<Pattern> = match pattern {
+        Some(pattern) => vec![pattern],
+        None => DEFAULT_PATTERNS.to_vec(),
+    }
+    .into_iter()
+    .filter_map(|v| Pattern::new(v).ok())
+    .collect();
+    let archive = ZipArchive::new(File::open(path)?)?;
+    let mut names: Vec<String> = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .filter(|name| patterns.iter().any(|v| v.matches(name)))
+        .map(|name| name.to_owned())
+        .collect();
+
+    names.sort();
+    Ok(names
+        .into_iter()
+        .map(|name| format!("{}{}{}", path, SEPARATOR, name))
+        .collect())
+}
+
+/// Read the content of entry `path` returned by [`entries`].
+pub fn read_entry(path: &str) -> std::io::Result<Vec<u8>> {
+    let (zip, name) = split_entry(path)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a zip entry"))?;
+    let mut archive = ZipArchive::new(File::open(zip)?)?;
+    let mut entry = archive.by_name(name)?;
+    let mut content = vec![];
+
+    entry.read_to_end(&mut content)?;
+    Ok(content)
+}